@@ -7,7 +7,7 @@ use fibers_http_server::{HandleRequest, Reply, Req, Res, ServerBuilder, Status};
 use futures::future::ok;
 use httpcodec::{BodyDecoder, BodyEncoder};
 use rustracing::sampler::AllSampler;
-use rustracing_jaeger::reporter::JaegerCompactReporter;
+use rustracing_jaeger::reporter::{JaegerCompactReporter, RemoteReporterBuilder};
 use rustracing_jaeger::span::SpanContext;
 use rustracing_jaeger::Tracer;
 use std::collections::HashMap;
@@ -47,12 +47,8 @@ fn main() -> trackable::result::MainResult {
     let (span_tx, span_rx) = crossbeam_channel::bounded(100);
     let tracer = Tracer::with_sender(AllSampler, span_tx);
     let handler = Hello { tracer };
-    std::thread::spawn(move || {
-        let reporter = track_try_unwrap!(JaegerCompactReporter::new("http_hello_server"));
-        for span in span_rx {
-            track_try_unwrap!(reporter.report(&[span]));
-        }
-    });
+    let reporter = track!(JaegerCompactReporter::new("http_hello_server"))?;
+    let _reporter = RemoteReporterBuilder::compact(reporter, span_rx).finish();
 
     let mut builder = ServerBuilder::new(track_any_err!("127.0.0.1:8081".parse())?);
     track!(builder.add_handler(handler))?;
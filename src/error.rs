@@ -20,3 +20,16 @@ pub fn from_thrift_error(f: thrift_codec::Error) -> Error {
         thrift_codec::ErrorKind::Other => ErrorKind::Other.cause(f).into(),
     }
 }
+
+#[cfg(feature = "grpc")]
+pub fn from_grpc_status(f: tonic::Status) -> Error {
+    match f.code() {
+        tonic::Code::InvalidArgument => ErrorKind::InvalidInput.cause(f).into(),
+        _ => ErrorKind::Other.cause(f).into(),
+    }
+}
+
+#[cfg(feature = "grpc")]
+pub fn from_grpc_transport_error(f: tonic::transport::Error) -> Error {
+    ErrorKind::Other.cause(f).into()
+}
@@ -0,0 +1,282 @@
+use crate::error;
+use crate::json::{self, Json};
+use crate::span::FinishedSpan;
+use crate::thrift::jaeger;
+use crate::Result;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// The process id of all events (i.e., the traced process).
+const PID: i64 = 1;
+
+/// The tags which denote the thread on which a span ran (used by `TrackGrouping::Thread`).
+const THREAD_TAG_KEYS: [&str; 2] = ["thread.name", "thread.id"];
+
+/// How spans are grouped into tracks (i.e., rows shown in the viewer).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrackGrouping {
+    /// A track per trace.
+    #[default]
+    Trace,
+
+    /// A track per thread.
+    ///
+    /// Since spans do not record threads by themselves, the thread is identified by
+    /// the `thread.name` or `thread.id` tag.
+    /// Spans which have neither tag are put into the same track.
+    Thread,
+}
+
+/// Writer which exports spans in the [Chrome Trace Event format].
+///
+/// The written file can be opened by `chrome://tracing` or [Perfetto UI].
+///
+/// Each span becomes a complete (`"X"`) event whose `args` are the tags of the span,
+/// and each log becomes an instant (`"i"`) event in the same track.
+/// The timestamps are microseconds since the UNIX epoch.
+///
+/// [Chrome Trace Event format]: https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU
+/// [Perfetto UI]: https://ui.perfetto.dev
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::export::ChromeTraceWriter;
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+///
+/// let mut writer = ChromeTraceWriter::new("sample_service");
+/// writer.add_spans(&span_rx.try_iter().collect::<Vec<_>>());
+///
+/// let mut buf = Vec::new();
+/// writer.write_to(&mut buf).unwrap();
+/// assert!(buf.starts_with(br#"{"traceEvents":["#));
+/// ```
+#[derive(Debug)]
+pub struct ChromeTraceWriter {
+    service_name: String,
+    grouping: TrackGrouping,
+    spans: Vec<jaeger::Span>,
+}
+impl ChromeTraceWriter {
+    /// Makes a new `ChromeTraceWriter` instance.
+    ///
+    /// `service_name` is used as the name of the process.
+    pub fn new(service_name: &str) -> Self {
+        ChromeTraceWriter {
+            service_name: service_name.to_owned(),
+            grouping: TrackGrouping::default(),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Sets how spans are grouped into tracks.
+    ///
+    /// The default value is `TrackGrouping::Trace`.
+    pub fn set_grouping(&mut self, grouping: TrackGrouping) {
+        self.grouping = grouping;
+    }
+
+    /// Adds `spans` to the spans to be written.
+    pub fn add_spans(&mut self, spans: &[FinishedSpan]) {
+        self.spans.extend(spans.iter().map(jaeger::Span::from));
+    }
+
+    /// Writes the added spans to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to write to `writer`,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        let json = self.to_json();
+        track!(write!(writer, "{}", json).map_err(error::from_io_error))?;
+        track!(writer.flush().map_err(error::from_io_error))?;
+        Ok(())
+    }
+
+    fn to_json(&self) -> Json {
+        let mut events = vec![metadata_event(
+            "process_name",
+            None,
+            self.service_name.as_str(),
+        )];
+        let mut tids: HashMap<String, i64> = HashMap::new();
+        for span in &self.spans {
+            let track = self.track_name(span);
+            let tid = match tids.get(&track) {
+                Some(&tid) => tid,
+                None => {
+                    let tid = tids.len() as i64 + 1;
+                    tids.insert(track.clone(), tid);
+                    events.push(metadata_event("thread_name", Some(tid), track));
+                    tid
+                }
+            };
+
+            let mut args = Json::object()
+                .with(
+                    "trace_id",
+                    json::trace_id(span.trace_id_high, span.trace_id_low),
+                )
+                .with("span_id", json::span_id(span.span_id));
+            for tag in &span.tags {
+                args.insert(tag.key(), json::tag_value(tag));
+            }
+            events.push(
+                Json::object()
+                    .with("name", span.operation_name.as_str())
+                    .with("cat", "span")
+                    .with("ph", "X")
+                    .with("ts", span.start_time)
+                    .with("dur", span.duration)
+                    .with("pid", PID)
+                    .with("tid", tid)
+                    .with("args", args),
+            );
+
+            for log in &span.logs {
+                let mut name = "log";
+                let mut args = Json::object();
+                for field in &log.fields {
+                    match field {
+                        jaeger::Tag::String { key, value } if key == "event" => name = value,
+                        _ => args.insert(field.key(), json::tag_value(field)),
+                    }
+                }
+                events.push(
+                    Json::object()
+                        .with("name", name)
+                        .with("cat", "log")
+                        .with("ph", "i")
+                        .with("s", "t")
+                        .with("ts", log.timestamp)
+                        .with("pid", PID)
+                        .with("tid", tid)
+                        .with("args", args),
+                );
+            }
+        }
+        Json::object()
+            .with("traceEvents", events)
+            .with("displayTimeUnit", "ms")
+    }
+
+    fn track_name(&self, span: &jaeger::Span) -> String {
+        match self.grouping {
+            TrackGrouping::Trace => format!(
+                "trace {}",
+                json::trace_id(span.trace_id_high, span.trace_id_low)
+            ),
+            TrackGrouping::Thread => {
+                let thread = THREAD_TAG_KEYS.iter().find_map(|key| {
+                    span.tags
+                        .iter()
+                        .find(|t| t.key() == *key)
+                        .map(json::tag_value_string)
+                });
+                match thread {
+                    Some(thread) => format!("thread {}", thread),
+                    None => "unknown thread".to_owned(),
+                }
+            }
+        }
+    }
+}
+
+fn metadata_event<V: Into<Json>>(name: &str, tid: Option<i64>, value: V) -> Json {
+    let mut event = Json::object()
+        .with("name", name)
+        .with("ph", "M")
+        .with("pid", PID);
+    if let Some(tid) = tid {
+        event.insert("tid", tid);
+    }
+    event.with("args", Json::object().with("name", value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::{SpanContextStateBuilder, TraceId};
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use rustracing::tag::Tag;
+    use trackable::result::TestResult;
+
+    fn write(writer: &ChromeTraceWriter) -> Result<String> {
+        let mut buf = Vec::new();
+        track!(writer.write_to(&mut buf))?;
+        Ok(String::from_utf8(buf).expect("Never fails"))
+    }
+
+    #[test]
+    fn chrome_trace_writer_works() -> TestResult {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        {
+            let state = SpanContextStateBuilder::new()
+                .trace_id(TraceId {
+                    high: 0,
+                    low: 0xabc,
+                })
+                .span_id(0x10)
+                .finish();
+            let parent = tracer
+                .span("parent")
+                .tag(Tag::new("n", 1))
+                .start_with_state(state);
+            let mut child = tracer.span("child").child_of(&parent).start();
+            child.log(|log| {
+                log.std().event("cache-miss").message("foo");
+            });
+        }
+        tracer.span("other").start();
+
+        let mut writer = ChromeTraceWriter::new("chrome_test");
+        writer.add_spans(&span_rx.try_iter().collect::<Vec<_>>());
+        let json = track!(write(&writer))?;
+        assert!(json.starts_with(concat!(
+            r#"{"traceEvents":[{"name":"process_name","ph":"M","pid":1,"args":{"name":"chrome_test"}},"#,
+            r#"{"name":"thread_name","ph":"M","pid":1,"tid":1,"args":{"name":"trace 0000000000000abc"}},"#,
+            r#"{"name":"child","cat":"span","ph":"X","ts":"#
+        )));
+        assert!(json.contains(r#","pid":1,"tid":1,"args":{"message":"foo"}}"#));
+        assert!(json.contains(r#"{"name":"cache-miss","cat":"log","ph":"i","s":"t","ts":"#));
+        assert!(json.contains(r#","pid":1,"tid":1,"args":{"trace_id":"0000000000000abc","span_id":"0000000000000010","n":1}}"#));
+        assert!(json.contains(r#"{"name":"other","cat":"span","ph":"X","ts":"#));
+        assert!(json.contains(r#","pid":1,"tid":2,"args":{"trace_id":""#));
+        assert!(json.ends_with(r#"],"displayTimeUnit":"ms"}"#));
+        Ok(())
+    }
+
+    #[test]
+    fn thread_grouping_works() -> TestResult {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer
+            .span("foo")
+            .tag(Tag::new("thread.name", "main"))
+            .start();
+        tracer.span("bar").tag(Tag::new("thread.id", 3)).start();
+        tracer
+            .span("baz")
+            .tag(Tag::new("thread.name", "main"))
+            .start();
+        tracer.span("qux").start();
+
+        let mut writer = ChromeTraceWriter::new("chrome_test");
+        writer.set_grouping(TrackGrouping::Thread);
+        writer.add_spans(&span_rx.try_iter().collect::<Vec<_>>());
+        let json = track!(write(&writer))?;
+        assert!(json.contains(r#""tid":1,"args":{"name":"thread main"}"#));
+        assert!(json.contains(r#""tid":2,"args":{"name":"thread 3"}"#));
+        assert!(json.contains(r#""tid":3,"args":{"name":"unknown thread"}"#));
+        assert_eq!(json.matches(r#""ph":"X","#).count(), 4);
+        assert_eq!(json.matches(r#""tid":1,"args":{"trace_id""#).count(), 2);
+        Ok(())
+    }
+}
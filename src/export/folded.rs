@@ -0,0 +1,230 @@
+use crate::error;
+use crate::span::{FinishedSpan, TraceId};
+use crate::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+/// Writer which exports spans as folded stacks
+/// (i.e., the input format of [inferno] and [flamegraph.pl]).
+///
+/// Each line consists of the operation names from a root span to a span separated by `;`,
+/// followed by the total self-time of the spans on that path in microseconds
+/// (e.g., `root;child;grandchild 120`).
+/// The self-time of a span is its duration minus the durations of its children
+/// (saturated at zero if the children run concurrently).
+///
+/// The parent of a span is the span referred by its first `ChildOf` reference.
+/// Spans whose parents have not been added are treated as roots,
+/// so all spans of a trace should be added before writing.
+///
+/// [inferno]: https://github.com/jonhoo/inferno
+/// [flamegraph.pl]: https://github.com/brendangregg/FlameGraph
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::export::FoldedStackWriter;
+/// use std::time::{Duration, SystemTime};
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// let now = SystemTime::now();
+/// {
+///     let parent = tracer.span("parent").start_time(now).start();
+///     let mut child = tracer.span("child").child_of(&parent).start_time(now).start();
+///     child.set_finish_time(|| now + Duration::from_millis(1));
+/// }
+///
+/// let mut writer = FoldedStackWriter::new();
+/// writer.add_spans(&span_rx.try_iter().collect::<Vec<_>>());
+/// assert_eq!(writer.stacks().get("parent;child"), Some(&1000));
+///
+/// let mut buf = Vec::new();
+/// writer.write_to(&mut buf).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct FoldedStackWriter {
+    spans: BTreeMap<(TraceId, u64), Node>,
+}
+impl FoldedStackWriter {
+    /// Makes a new `FoldedStackWriter` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `spans` to the spans to be aggregated.
+    pub fn add_spans(&mut self, spans: &[FinishedSpan]) {
+        for span in spans {
+            let state = span.context().state();
+            let parent = span
+                .references()
+                .iter()
+                .find(|r| r.is_child_of())
+                .map(|r| r.span().span_id());
+            let duration = span
+                .finish_time()
+                .duration_since(span.start_time())
+                .unwrap_or_default();
+            let node = Node {
+                parent,
+                name: frame_name(span.operation_name()),
+                duration: duration.as_micros() as u64,
+            };
+            self.spans.insert((state.trace_id(), state.span_id()), node);
+        }
+    }
+
+    /// Returns the aggregated self-times (in microseconds) keyed by the folded stacks.
+    ///
+    /// Stacks whose self-times are zero are omitted.
+    pub fn stacks(&self) -> BTreeMap<String, u64> {
+        let mut children_time: BTreeMap<(TraceId, u64), u64> = BTreeMap::new();
+        for (&(trace_id, _), node) in &self.spans {
+            if let Some(parent) = self.parent_key(trace_id, node) {
+                *children_time.entry(parent).or_default() += node.duration;
+            }
+        }
+
+        let mut stacks = BTreeMap::new();
+        for (key, node) in &self.spans {
+            let children = children_time.get(key).copied().unwrap_or(0);
+            let self_time = node.duration.saturating_sub(children);
+            if self_time == 0 {
+                continue;
+            }
+            *stacks.entry(self.stack(*key)).or_default() += self_time;
+        }
+        stacks
+    }
+
+    /// Writes the folded stacks to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to write to `writer`,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        for (stack, micros) in self.stacks() {
+            track!(writeln!(writer, "{} {}", stack, micros).map_err(error::from_io_error))?;
+        }
+        track!(writer.flush().map_err(error::from_io_error))?;
+        Ok(())
+    }
+
+    fn parent_key(&self, trace_id: TraceId, node: &Node) -> Option<(TraceId, u64)> {
+        node.parent
+            .map(|span_id| (trace_id, span_id))
+            .filter(|key| self.spans.contains_key(key))
+    }
+
+    fn stack(&self, mut key: (TraceId, u64)) -> String {
+        let mut frames = Vec::new();
+        let mut visited = BTreeSet::new();
+        while visited.insert(key) {
+            let node = &self.spans[&key];
+            frames.push(node.name.as_str());
+            match self.parent_key(key.0, node) {
+                Some(parent) => key = parent,
+                None => break,
+            }
+        }
+        frames.reverse();
+        frames.join(";")
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    parent: Option<u64>,
+    name: String,
+    duration: u64,
+}
+
+/// Replaces the characters which have special meanings in the folded format.
+fn frame_name(operation_name: &str) -> String {
+    operation_name
+        .chars()
+        .map(|c| match c {
+            ';' => ':',
+            '\n' | '\r' => ' ',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::SpanContextStateBuilder;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use std::time::{Duration, SystemTime};
+    use trackable::result::TestResult;
+
+    #[test]
+    fn folded_stack_writer_works() -> TestResult {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        let t0 = SystemTime::now();
+        let ms = Duration::from_millis;
+        for low in [1, 2] {
+            let state = SpanContextStateBuilder::new()
+                .trace_id(TraceId { high: 0, low })
+                .finish();
+            let mut root = tracer.span("root").start_time(t0).start_with_state(state);
+            root.set_finish_time(|| t0 + ms(10));
+            {
+                let mut child = tracer
+                    .span("child")
+                    .child_of(&root)
+                    .start_time(t0 + ms(1))
+                    .start();
+                child.set_finish_time(|| t0 + ms(5));
+                let mut grandchild = tracer
+                    .span("grand;child")
+                    .child_of(&child)
+                    .start_time(t0 + ms(2))
+                    .start();
+                grandchild.set_finish_time(|| t0 + ms(3));
+            }
+            let mut follower = tracer
+                .span("follower")
+                .follows_from(&root)
+                .start_time(t0 + ms(10))
+                .start();
+            follower.set_finish_time(|| t0 + ms(12));
+        }
+
+        let mut writer = FoldedStackWriter::new();
+        writer.add_spans(&span_rx.try_iter().collect::<Vec<_>>());
+
+        let mut buf = Vec::new();
+        track!(writer.write_to(&mut buf))?;
+        let folded = track_any_err!(String::from_utf8(buf))?;
+        assert_eq!(
+            folded,
+            "follower 4000\nroot 12000\nroot;child 6000\nroot;child;grand:child 2000\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn missing_parent_is_treated_as_root() {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        let t0 = SystemTime::now();
+        {
+            let parent = tracer.span("parent").start();
+            let mut child = tracer
+                .span("child")
+                .child_of(&parent)
+                .start_time(t0)
+                .start();
+            child.set_finish_time(|| t0 + Duration::from_micros(7));
+        }
+        let spans = span_rx.try_iter().collect::<Vec<_>>();
+
+        let mut writer = FoldedStackWriter::new();
+        writer.add_spans(&spans[..1]);
+        assert_eq!(writer.stacks().get("child"), Some(&7));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::error;
+use crate::json::{self, Json};
+use crate::span::{FinishedSpan, TraceId};
+use crate::thrift::jaeger;
+use crate::Result;
+use rustracing::tag::Tag;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// The id of the process of the spans in the written traces.
+const PROCESS_ID: &str = "p1";
+
+/// Writer which exports spans in the JSON format returned by the `/api/traces` endpoint of
+/// the Jaeger query service.
+///
+/// The written file can be opened by Jaeger UI (via "JSON File" in the search page)
+/// without running any backend.
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::export::JaegerUiWriter;
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+///
+/// let mut writer = JaegerUiWriter::new("sample_service");
+/// writer.add_spans(&span_rx.try_iter().collect::<Vec<_>>());
+///
+/// let mut buf = Vec::new();
+/// writer.write_to(&mut buf).unwrap();
+/// assert!(buf.starts_with(br#"{"data":[{"traceID":"#));
+/// ```
+#[derive(Debug)]
+pub struct JaegerUiWriter {
+    process: jaeger::Process,
+    traces: BTreeMap<TraceId, Vec<jaeger::Span>>,
+}
+impl JaegerUiWriter {
+    /// Makes a new `JaegerUiWriter` instance.
+    pub fn new(service_name: &str) -> Self {
+        JaegerUiWriter {
+            process: crate::reporter::default_process(service_name),
+            traces: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `tag` to this service.
+    pub fn add_service_tag(&mut self, tag: Tag) {
+        self.process.tags.push((&tag).into());
+    }
+
+    /// Adds `spans` to the traces to be written.
+    ///
+    /// The spans are grouped by their trace ids.
+    pub fn add_spans(&mut self, spans: &[FinishedSpan]) {
+        for span in spans {
+            let trace_id = span.context().state().trace_id();
+            self.traces
+                .entry(trace_id)
+                .or_default()
+                .push(jaeger::Span::from(span));
+        }
+    }
+
+    /// Returns the number of the traces to be written.
+    pub fn trace_count(&self) -> usize {
+        self.traces.len()
+    }
+
+    /// Writes the added traces to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to write to `writer`,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        let json = self.to_json();
+        track!(write!(writer, "{}", json).map_err(error::from_io_error))?;
+        track!(writer.flush().map_err(error::from_io_error))?;
+        Ok(())
+    }
+
+    fn to_json(&self) -> Json {
+        let process = json::jaeger_process(self.process.clone());
+        let data = self
+            .traces
+            .iter()
+            .map(|(trace_id, spans)| {
+                let spans = spans
+                    .iter()
+                    .map(|span| {
+                        json::jaeger_span(span.clone())
+                            .with("processID", PROCESS_ID)
+                            .with("warnings", Json::Null)
+                    })
+                    .collect::<Vec<_>>();
+                Json::object()
+                    .with(
+                        "traceID",
+                        json::trace_id(trace_id.high as i64, trace_id.low as i64),
+                    )
+                    .with("spans", spans)
+                    .with(
+                        "processes",
+                        Json::object().with(PROCESS_ID, process.clone()),
+                    )
+                    .with("warnings", Json::Null)
+            })
+            .collect::<Vec<_>>();
+        Json::object()
+            .with("data", data)
+            .with("total", Json::Int(0))
+            .with("limit", Json::Int(0))
+            .with("offset", Json::Int(0))
+            .with("errors", Json::Null)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::SpanContextStateBuilder;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn jaeger_ui_writer_works() -> TestResult {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        for low in [2, 1] {
+            let state = SpanContextStateBuilder::new()
+                .trace_id(TraceId { high: 0, low })
+                .span_id(low * 16)
+                .finish();
+            let parent = tracer.span("parent").start_with_state(state);
+            tracer.span("child").child_of(&parent).start();
+        }
+
+        let mut writer = JaegerUiWriter::new("ui_test");
+        writer.add_spans(&span_rx.try_iter().collect::<Vec<_>>());
+        assert_eq!(writer.trace_count(), 2);
+
+        let mut buf = Vec::new();
+        track!(writer.write_to(&mut buf))?;
+        let json = track_any_err!(String::from_utf8(buf))?;
+        assert!(json.starts_with(
+            r#"{"data":[{"traceID":"0000000000000001","spans":[{"traceID":"0000000000000001","#
+        ));
+        assert!(json.contains(r#""operationName":"child","references":[{"refType":"CHILD_OF","traceID":"0000000000000001","spanID":"0000000000000010"}],"#));
+        assert!(json.contains(r#""processID":"p1","warnings":null}],"processes":{"p1":{"serviceName":"ui_test","tags":["#));
+        assert!(json.contains(r#"{"traceID":"0000000000000002","spans":[{"#));
+        assert!(json.ends_with(r#"],"total":0,"limit":0,"offset":0,"errors":null}"#));
+        assert_eq!(json.matches(r#""spanID":"0000000000000020""#).count(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+//! Exporters which write finished spans in the file formats of other tools.
+pub use self::chrome::{ChromeTraceWriter, TrackGrouping};
+pub use self::folded::FoldedStackWriter;
+pub use self::jaeger_ui::JaegerUiWriter;
+
+mod chrome;
+mod folded;
+mod jaeger_ui;
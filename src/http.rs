@@ -0,0 +1,418 @@
+//! Minimal HTTP/1.1 client used by the reporters which talk to collectors directly.
+//!
+//! Only plain `http://` URLs are supported.
+use crate::error;
+use crate::{ErrorKind, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Parsed `http://` URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Url {
+    host: String,
+    port: u16,
+    path: String,
+}
+impl Url {
+    /// Parses `url` (e.g., `"http://localhost:14268/api/traces"`).
+    pub(crate) fn parse(url: &str) -> Result<Self> {
+        let rest = track_assert_some!(
+            url.strip_prefix("http://"),
+            ErrorKind::InvalidInput,
+            "Only http:// URLs are supported: url={:?}",
+            url
+        );
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rfind(':') {
+            Some(i) if !authority[i..].contains(']') => {
+                let port = track!(authority[i + 1..]
+                    .parse::<u16>()
+                    .map_err(error::from_parse_int_error))?;
+                (&authority[..i], port)
+            }
+            _ => (authority, 80),
+        };
+        track_assert!(!host.is_empty(), ErrorKind::InvalidInput, "url={:?}", url);
+        Ok(Url {
+            host: host.to_owned(),
+            port,
+            path: path.to_owned(),
+        })
+    }
+
+    fn host_header(&self) -> String {
+        if self.port == 80 {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// The maximum size of a response body.
+///
+/// Only the status and a short error message are needed from collectors,
+/// so larger bodies are rejected instead of being allocated.
+const MAX_RESPONSE_BODY_SIZE: usize = 64 * 1024;
+
+/// The headers which are always written by `post` and can not be set by users.
+const RESERVED_HEADERS: [&str; 4] = ["Host", "Content-Length", "Connection", "Transfer-Encoding"];
+
+/// HTTP response.
+#[derive(Debug)]
+pub(crate) struct Response {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+impl Response {
+    pub(crate) fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// Checks that `name: value` can be written as a header line without breaking the request.
+///
+/// `name` must be a token (RFC 9110) other than the framing headers written by `post`
+/// (e.g., `Content-Length`), and `value` must not contain CR, LF or NUL.
+pub(crate) fn validate_header(name: &str, value: &str) -> Result<()> {
+    let is_tchar = |b: u8| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b);
+    track_assert!(
+        !name.is_empty() && name.bytes().all(is_tchar),
+        ErrorKind::InvalidInput,
+        "Invalid header name: {:?}",
+        name
+    );
+    track_assert!(
+        !RESERVED_HEADERS
+            .iter()
+            .any(|h| h.eq_ignore_ascii_case(name)),
+        ErrorKind::InvalidInput,
+        "Reserved header name: {:?}",
+        name
+    );
+    track_assert!(
+        !value.bytes().any(|b| matches!(b, b'\r' | b'\n' | 0)),
+        ErrorKind::InvalidInput,
+        "Invalid header value: name={:?}, value={:?}",
+        name,
+        value
+    );
+    Ok(())
+}
+
+/// Sends a POST request and returns the response.
+///
+/// `timeout` is applied to each of connecting, writing the request and reading the response.
+pub(crate) fn post(
+    url: &Url,
+    headers: &[(String, String)],
+    body: &[u8],
+    timeout: Duration,
+) -> Result<Response> {
+    let mut stream = track!(connect(url, timeout))?;
+    track!(stream
+        .set_write_timeout(Some(timeout))
+        .map_err(error::from_io_error))?;
+    track!(stream
+        .set_read_timeout(Some(timeout))
+        .map_err(error::from_io_error))?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        url.path,
+        url.host_header(),
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    track!(stream
+        .write_all(request.as_bytes())
+        .map_err(error::from_io_error))?;
+    track!(stream.write_all(body).map_err(error::from_io_error))?;
+    track!(stream.flush().map_err(error::from_io_error))?;
+
+    track!(read_response(BufReader::new(stream)))
+}
+
+fn connect(url: &Url, timeout: Duration) -> Result<TcpStream> {
+    let host = url.host.trim_start_matches('[').trim_end_matches(']');
+    let addrs = track!((host, url.port)
+        .to_socket_addrs()
+        .map_err(error::from_io_error))?;
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    match last_error {
+        Some(e) => Err(track!(error::from_io_error(e))),
+        None => track_panic!(ErrorKind::InvalidInput, "Unresolved host: {:?}", url.host),
+    }
+}
+
+fn read_response<R: BufRead>(mut reader: R) -> Result<Response> {
+    let status_line = track!(read_line(&mut reader))?;
+    let mut tokens = status_line.splitn(3, ' ');
+    let version = tokens.next().unwrap_or("");
+    track_assert!(
+        version.starts_with("HTTP/1."),
+        ErrorKind::Other,
+        "Unexpected status line: {:?}",
+        status_line
+    );
+    let status = track!(tokens
+        .next()
+        .unwrap_or("")
+        .parse::<u16>()
+        .map_err(error::from_parse_int_error))?;
+
+    let mut content_length = None;
+    let mut chunked = false;
+    loop {
+        let line = track!(read_line(&mut reader))?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = Some(track!(value
+                    .parse::<usize>()
+                    .map_err(error::from_parse_int_error))?);
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                chunked = value.eq_ignore_ascii_case("chunked");
+            }
+        }
+    }
+
+    let mut body = Vec::new();
+    if chunked {
+        loop {
+            let line = track!(read_line(&mut reader))?;
+            let size = line.split(';').next().unwrap_or("").trim();
+            let size =
+                track!(usize::from_str_radix(size, 16).map_err(error::from_parse_int_error))?;
+            if size == 0 {
+                break;
+            }
+            track!(check_body_size(body.len().saturating_add(size)))?;
+            let start = body.len();
+            body.resize(start + size, 0);
+            track!(reader
+                .read_exact(&mut body[start..])
+                .map_err(error::from_io_error))?;
+            track!(read_line(&mut reader))?;
+        }
+    } else if let Some(n) = content_length {
+        track!(check_body_size(n))?;
+        body.resize(n, 0);
+        track!(reader.read_exact(&mut body).map_err(error::from_io_error))?;
+    } else {
+        let limit = MAX_RESPONSE_BODY_SIZE as u64 + 1;
+        track!(reader
+            .take(limit)
+            .read_to_end(&mut body)
+            .map_err(error::from_io_error))?;
+        track!(check_body_size(body.len()))?;
+    }
+    Ok(Response { status, body })
+}
+
+fn check_body_size(size: usize) -> Result<()> {
+    track_assert!(
+        size <= MAX_RESPONSE_BODY_SIZE,
+        ErrorKind::Other,
+        "Too large response body: size={}, max={}",
+        size,
+        MAX_RESPONSE_BODY_SIZE
+    );
+    Ok(())
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String> {
+    let mut line = String::new();
+    track!(reader.read_line(&mut line).map_err(error::from_io_error))?;
+    Ok(line.trim_end_matches(&['\r', '\n'][..]).to_owned())
+}
+
+/// Local HTTP server which records the received requests (for testing).
+#[cfg(test)]
+pub(crate) mod test_server {
+    use super::*;
+    use std::io::Read;
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// Received request.
+    #[derive(Debug)]
+    pub struct Request {
+        pub method: String,
+        pub path: String,
+        pub headers: Vec<(String, String)>,
+        pub body: Vec<u8>,
+    }
+    impl Request {
+        pub fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        }
+    }
+
+    /// Starts a server which responds to each request with `status`.
+    pub fn start(status: u16) -> Result<(SocketAddr, mpsc::Receiver<Request>)> {
+        let listener = track!(TcpListener::bind("127.0.0.1:0").map_err(error::from_io_error))?;
+        let addr = track!(listener.local_addr().map_err(error::from_io_error))?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                let Ok(request) = read_request(&mut stream) else {
+                    continue;
+                };
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                let _ = stream.write_all(response.as_bytes());
+                if tx.send(request).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok((addr, rx))
+    }
+
+    fn read_request(stream: &mut TcpStream) -> Result<Request> {
+        let mut reader = BufReader::new(stream);
+        let request_line = track!(read_line(&mut reader))?;
+        let mut tokens = request_line.split(' ');
+        let method = tokens.next().unwrap_or("").to_owned();
+        let path = tokens.next().unwrap_or("").to_owned();
+        let mut headers = Vec::new();
+        loop {
+            let line = track!(read_line(&mut reader))?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_owned(), value.trim().to_owned()));
+            }
+        }
+        let mut request = Request {
+            method,
+            path,
+            headers,
+            body: Vec::new(),
+        };
+        let n = request
+            .header("content-length")
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(0);
+        request.body.resize(n, 0);
+        track!(reader
+            .read_exact(&mut request.body)
+            .map_err(error::from_io_error))?;
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn parse_url_works() -> TestResult {
+        let url = track!(Url::parse("http://localhost:14268/api/traces"))?;
+        assert_eq!(url.host, "localhost");
+        assert_eq!(url.port, 14268);
+        assert_eq!(url.path, "/api/traces");
+
+        let url = track!(Url::parse("http://[::1]"))?;
+        assert_eq!(url.host, "[::1]");
+        assert_eq!(url.port, 80);
+        assert_eq!(url.path, "/");
+
+        assert!(Url::parse("https://localhost/").is_err());
+        assert!(Url::parse("http://localhost:foo/").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn read_chunked_response_works() -> TestResult {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nfoo\r\n3\r\nbar\r\n0\r\n\r\n";
+        let response = track!(read_response(&response[..]))?;
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"foobar");
+        Ok(())
+    }
+
+    #[test]
+    fn too_large_response_body_is_rejected() {
+        let responses = [
+            "HTTP/1.1 500 Error\r\nContent-Length: 18446744073709551615\r\n\r\n".to_owned(),
+            format!(
+                "HTTP/1.1 500 Error\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n",
+                usize::MAX
+            ),
+            format!(
+                "HTTP/1.1 500 Error\r\n\r\n{}",
+                "a".repeat(MAX_RESPONSE_BODY_SIZE + 1)
+            ),
+        ];
+        for response in &responses {
+            let e = read_response(response.as_bytes()).err().unwrap();
+            assert_eq!(*e.kind(), ErrorKind::Other);
+        }
+    }
+
+    #[test]
+    fn validate_header_works() {
+        assert!(validate_header("X-Foo", "bar baz").is_ok());
+        assert!(validate_header("X-Foo", "").is_ok());
+        for (name, value) in [
+            ("", "bar"),
+            ("X Foo", "bar"),
+            ("X-Foo:", "bar"),
+            ("X-Foo\r\nX-Bar", "baz"),
+            ("X-Foo", "bar\r\nX-Bar: baz"),
+            ("X-Foo", "bar\n"),
+            ("X-Foo", "bar\0"),
+            ("Host", "example.com"),
+            ("content-length", "0"),
+            ("CONNECTION", "keep-alive"),
+            ("Transfer-Encoding", "chunked"),
+        ] {
+            let e = validate_header(name, value).err().unwrap();
+            assert_eq!(*e.kind(), ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn post_works() -> TestResult {
+        let (addr, rx) = track!(test_server::start(202))?;
+        let url = track!(Url::parse(&format!("http://{}/foo", addr)))?;
+        let headers = vec![("X-Foo".to_owned(), "bar".to_owned())];
+        let response = track!(post(&url, &headers, b"baz", Duration::from_secs(5)))?;
+        assert_eq!(response.status, 202);
+        assert!(response.is_success());
+
+        let request = track_any_err!(rx.recv_timeout(Duration::from_secs(5)))?;
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/foo");
+        assert_eq!(request.header("x-foo"), Some("bar"));
+        assert_eq!(request.body, b"baz");
+        Ok(())
+    }
+}
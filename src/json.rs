@@ -0,0 +1,356 @@
+//! Minimal JSON writer used by the reporters which emit JSON.
+//!
+//! This also defines the JSON representation of the jaeger.thrift data model
+//! (the same as the one used by the Jaeger query service).
+use crate::thrift::jaeger;
+use std::fmt;
+
+/// JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+impl Json {
+    /// Makes an empty object.
+    pub(crate) fn object() -> Self {
+        Json::Object(Vec::new())
+    }
+
+    /// Adds the member `key: value` to this object.
+    pub(crate) fn with<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<Json>,
+    {
+        self.insert(key, value);
+        self
+    }
+
+    /// Adds the member `key: value` to this object.
+    ///
+    /// This does nothing if this value is not an object.
+    pub(crate) fn insert<K, V>(&mut self, key: K, value: V)
+    where
+        K: Into<String>,
+        V: Into<Json>,
+    {
+        if let Json::Object(members) = self {
+            members.push((key.into(), value.into()));
+        }
+    }
+}
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(v) => write!(f, "{}", v),
+            Json::Int(v) => write!(f, "{}", v),
+            Json::Float(v) if v.is_finite() => write!(f, "{:?}", v),
+            Json::Float(_) => write!(f, "null"),
+            Json::String(v) => write_string(f, v),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+impl From<bool> for Json {
+    fn from(f: bool) -> Self {
+        Json::Bool(f)
+    }
+}
+impl From<i64> for Json {
+    fn from(f: i64) -> Self {
+        Json::Int(f)
+    }
+}
+impl From<f64> for Json {
+    fn from(f: f64) -> Self {
+        Json::Float(f)
+    }
+}
+impl<'a> From<&'a str> for Json {
+    fn from(f: &'a str) -> Self {
+        Json::String(f.to_owned())
+    }
+}
+impl From<String> for Json {
+    fn from(f: String) -> Self {
+        Json::String(f)
+    }
+}
+impl From<Vec<Json>> for Json {
+    fn from(f: Vec<Json>) -> Self {
+        Json::Array(f)
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Returns the hex representation of a trace id.
+///
+/// It consists of 16 characters if `high` is zero, 32 otherwise.
+pub(crate) fn trace_id(high: i64, low: i64) -> String {
+    if high == 0 {
+        format!("{:016x}", low)
+    } else {
+        format!("{:016x}{:016x}", high, low)
+    }
+}
+
+/// Returns the hex representation of a span id.
+pub(crate) fn span_id(id: i64) -> String {
+    format!("{:016x}", id)
+}
+
+/// Converts `span` to the JSON object which has
+/// `traceID`, `spanID`, `operationName`, `references`, `flags`, `startTime`, `duration`, `tags` and `logs`.
+pub(crate) fn jaeger_span(span: jaeger::Span) -> Json {
+    let references = span
+        .references
+        .into_iter()
+        .map(|r| {
+            let ref_type = match r.kind {
+                jaeger::SpanRefKind::ChildOf => "CHILD_OF",
+                jaeger::SpanRefKind::FollowsFrom => "FOLLOWS_FROM",
+            };
+            Json::object()
+                .with("refType", ref_type)
+                .with("traceID", trace_id(r.trace_id_high, r.trace_id_low))
+                .with("spanID", span_id(r.span_id))
+        })
+        .collect::<Vec<_>>();
+    let logs = span
+        .logs
+        .into_iter()
+        .map(|log| {
+            Json::object()
+                .with("timestamp", log.timestamp)
+                .with("fields", jaeger_tags(log.fields))
+        })
+        .collect::<Vec<_>>();
+    Json::object()
+        .with("traceID", trace_id(span.trace_id_high, span.trace_id_low))
+        .with("spanID", span_id(span.span_id))
+        .with("operationName", span.operation_name)
+        .with("references", references)
+        .with("flags", i64::from(span.flags))
+        .with("startTime", span.start_time)
+        .with("duration", span.duration)
+        .with("tags", jaeger_tags(span.tags))
+        .with("logs", logs)
+}
+
+/// Converts `process` to the JSON object which has `serviceName` and `tags`.
+pub(crate) fn jaeger_process(process: jaeger::Process) -> Json {
+    Json::object()
+        .with("serviceName", process.service_name)
+        .with("tags", jaeger_tags(process.tags))
+}
+
+fn jaeger_tags(tags: Vec<jaeger::Tag>) -> Json {
+    tags.iter()
+        .map(|tag| {
+            let value_type = match tag {
+                jaeger::Tag::String { .. } => "string",
+                jaeger::Tag::Double { .. } => "float64",
+                jaeger::Tag::Bool { .. } => "bool",
+                jaeger::Tag::Long { .. } => "int64",
+                jaeger::Tag::Binary { .. } => "binary",
+            };
+            Json::object()
+                .with("key", tag.key())
+                .with("type", value_type)
+                .with("value", tag_value(tag))
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Converts the value of `tag` to the JSON value of the corresponding type.
+///
+/// Binary values are encoded in base64.
+pub(crate) fn tag_value(tag: &jaeger::Tag) -> Json {
+    match tag {
+        jaeger::Tag::String { value, .. } => Json::from(value.as_str()),
+        jaeger::Tag::Double { value, .. } => Json::from(*value),
+        jaeger::Tag::Bool { value, .. } => Json::from(*value),
+        jaeger::Tag::Long { value, .. } => Json::from(*value),
+        jaeger::Tag::Binary { value, .. } => Json::from(base64(value)),
+    }
+}
+
+/// Returns the value of `tag` as a plain string (i.e., strings are not quoted).
+///
+/// Binary values are encoded in base64.
+pub(crate) fn tag_value_string(tag: &jaeger::Tag) -> String {
+    match tag_value(tag) {
+        Json::String(value) => value,
+        Json::Float(value) => value.to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Encodes `bytes` in the standard base64 encoding with padding.
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut s = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - i * 8));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                s.push(TABLE[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display_works() {
+        let json = Json::object()
+            .with("null", Json::Null)
+            .with("bool", true)
+            .with("int", Json::Int(-1))
+            .with("float", 1.5)
+            .with("nan", f64::NAN)
+            .with("string", "a\"b\\c\nd\u{1}")
+            .with("array", vec![Json::Int(1), Json::from("2")]);
+        assert_eq!(
+            json.to_string(),
+            r#"{"null":null,"bool":true,"int":-1,"float":1.5,"nan":null,"string":"a\"b\\c\nd\u0001","array":[1,"2"]}"#
+        );
+    }
+
+    #[test]
+    fn base64_works() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn tag_value_works() {
+        let tags = [
+            jaeger::Tag::String {
+                key: "s".to_owned(),
+                value: "a\"b".to_owned(),
+            },
+            jaeger::Tag::Double {
+                key: "d".to_owned(),
+                value: 1.5,
+            },
+            jaeger::Tag::Bool {
+                key: "b".to_owned(),
+                value: true,
+            },
+            jaeger::Tag::Long {
+                key: "l".to_owned(),
+                value: -3,
+            },
+            jaeger::Tag::Binary {
+                key: "x".to_owned(),
+                value: b"foo".to_vec(),
+            },
+        ];
+        let json = tags.iter().map(tag_value).collect::<Vec<_>>();
+        assert_eq!(
+            Json::from(json).to_string(),
+            r#"["a\"b",1.5,true,-3,"Zm9v"]"#
+        );
+
+        let strings = tags.iter().map(tag_value_string).collect::<Vec<_>>();
+        assert_eq!(strings, ["a\"b", "1.5", "true", "-3", "Zm9v"]);
+    }
+
+    #[test]
+    fn jaeger_span_works() {
+        let span = jaeger::Span {
+            trace_id_low: 2,
+            trace_id_high: 1,
+            span_id: 3,
+            parent_span_id: 4,
+            operation_name: "foo".to_owned(),
+            references: vec![jaeger::SpanRef {
+                kind: jaeger::SpanRefKind::ChildOf,
+                trace_id_low: 2,
+                trace_id_high: 1,
+                span_id: 4,
+            }],
+            flags: 1,
+            start_time: 100,
+            duration: 20,
+            tags: vec![jaeger::Tag::Long {
+                key: "n".to_owned(),
+                value: 10,
+            }],
+            logs: vec![jaeger::Log {
+                timestamp: 110,
+                fields: vec![jaeger::Tag::String {
+                    key: "event".to_owned(),
+                    value: "bar".to_owned(),
+                }],
+            }],
+        };
+        assert_eq!(
+            jaeger_span(span).to_string(),
+            concat!(
+                r#"{"traceID":"00000000000000010000000000000002","spanID":"0000000000000003","#,
+                r#""operationName":"foo","#,
+                r#""references":[{"refType":"CHILD_OF","traceID":"00000000000000010000000000000002","spanID":"0000000000000004"}],"#,
+                r#""flags":1,"startTime":100,"duration":20,"#,
+                r#""tags":[{"key":"n","type":"int64","value":10}],"#,
+                r#""logs":[{"timestamp":110,"fields":[{"key":"event","type":"string","value":"bar"}]}]}"#
+            )
+        );
+    }
+}
@@ -35,12 +35,18 @@ pub use self::span::Span;
 pub use self::tracer::Tracer;
 pub use rustracing::{Error, ErrorKind, Result};
 
+pub mod export;
+pub mod metrics;
+#[cfg(any(feature = "grpc", feature = "otlp"))]
+pub mod proto;
 pub mod reporter;
 pub mod span;
 pub mod thrift;
 
 mod constants;
 mod error;
+mod http;
+mod json;
 mod tracer;
 
 #[cfg(test)]
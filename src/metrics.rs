@@ -0,0 +1,318 @@
+//! RED (rate, errors and duration) metrics derived from finished spans.
+use crate::error;
+use crate::reporter::Reporter;
+use crate::span::FinishedSpan;
+use crate::Result;
+use rustracing::tag::TagValue;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::sync::{Mutex, MutexGuard};
+
+/// The default upper bounds (in seconds) of the buckets of the duration histograms.
+pub const DEFAULT_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Span processor which aggregates RED metrics per service and operation name.
+///
+/// The following metrics are rendered in the [Prometheus text exposition format]
+/// with the `service` and `operation` labels:
+/// - `spans_total`: the number of the spans
+/// - `span_errors_total`: the number of the spans which have the `error=true` tag
+/// - `span_duration_seconds`: the histogram of the durations of the spans
+///
+/// `SpanMetrics` also implements `Reporter`,
+/// so it can be combined with other reporters by `FanOutReporter`.
+///
+/// [Prometheus text exposition format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::metrics::SpanMetrics;
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+///
+/// let metrics = SpanMetrics::new("sample_service");
+/// metrics.record(&span_rx.try_iter().collect::<Vec<_>>());
+/// assert!(metrics
+///     .render()
+///     .contains(r#"spans_total{service="sample_service",operation="sample_op"} 1"#));
+/// ```
+#[derive(Debug)]
+pub struct SpanMetrics {
+    service_name: String,
+    buckets: Vec<f64>,
+    include_unsampled: bool,
+    series: Mutex<BTreeMap<(String, String), Series>>,
+}
+impl SpanMetrics {
+    /// Makes a new `SpanMetrics` instance.
+    ///
+    /// `service_name` is used as the `service` label of the spans given to `record`.
+    pub fn new(service_name: &str) -> Self {
+        SpanMetrics {
+            service_name: service_name.to_owned(),
+            buckets: DEFAULT_BUCKETS.to_vec(),
+            include_unsampled: false,
+            series: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Sets the upper bounds (in seconds) of the buckets of the duration histograms.
+    ///
+    /// The bounds are sorted, and the `+Inf` bucket is always added.
+    /// Since the existing histograms can not be re-bucketed, the recorded metrics are cleared.
+    ///
+    /// The default value is `DEFAULT_BUCKETS`.
+    pub fn set_buckets(&mut self, mut buckets: Vec<f64>) {
+        buckets.retain(|b| b.is_finite());
+        buckets.sort_by(|a, b| a.total_cmp(b));
+        buckets.dedup();
+        self.buckets = buckets;
+        self.lock_series().clear();
+    }
+
+    /// Sets whether spans which are not sampled are also aggregated.
+    ///
+    /// Such spans are finished when a local sampler records them
+    /// although the sampled flag in their contexts is off
+    /// (e.g., it was propagated from an upstream service which decided not to sample).
+    ///
+    /// The default value is `false`.
+    pub fn set_include_unsampled(&mut self, include: bool) {
+        self.include_unsampled = include;
+    }
+
+    /// Aggregates `spans` as the spans of the service given to `new`.
+    pub fn record(&self, spans: &[FinishedSpan]) {
+        self.record_service_spans(&self.service_name, spans);
+    }
+
+    /// Aggregates `spans` as the spans of the service `service_name`.
+    ///
+    /// This is useful to aggregate the spans of multiple tracers by a single `SpanMetrics`.
+    pub fn record_service_spans(&self, service_name: &str, spans: &[FinishedSpan]) {
+        let mut series = self.lock_series();
+        for span in spans {
+            if !self.include_unsampled && !span.context().state().is_sampled() {
+                continue;
+            }
+            let duration = span
+                .finish_time()
+                .duration_since(span.start_time())
+                .unwrap_or_default()
+                .as_secs_f64();
+            let is_error = span
+                .tags()
+                .iter()
+                .any(|t| t.name() == "error" && *t.value() == TagValue::Boolean(true));
+            series
+                .entry((service_name.to_owned(), span.operation_name().to_owned()))
+                .or_insert_with(|| Series::new(self.buckets.len()))
+                .observe(&self.buckets, duration, is_error);
+        }
+    }
+
+    /// Renders the aggregated metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let series = self.lock_series();
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP spans_total The number of the finished spans.");
+        let _ = writeln!(out, "# TYPE spans_total counter");
+        for (key, s) in series.iter() {
+            let _ = writeln!(out, "spans_total{{{}}} {}", labels(key), s.count);
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP span_errors_total The number of the finished spans which have the error tag."
+        );
+        let _ = writeln!(out, "# TYPE span_errors_total counter");
+        for (key, s) in series.iter() {
+            let _ = writeln!(out, "span_errors_total{{{}}} {}", labels(key), s.errors);
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP span_duration_seconds The durations of the finished spans."
+        );
+        let _ = writeln!(out, "# TYPE span_duration_seconds histogram");
+        for (key, s) in series.iter() {
+            let labels = labels(key);
+            let mut cumulative = 0;
+            for (bound, count) in self.buckets.iter().zip(&s.bucket_counts) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "span_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "span_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, s.count
+            );
+            let _ = writeln!(out, "span_duration_seconds_sum{{{}}} {}", labels, s.sum);
+            let _ = writeln!(out, "span_duration_seconds_count{{{}}} {}", labels, s.count);
+        }
+        out
+    }
+
+    /// Writes the aggregated metrics to `writer` in the Prometheus text exposition format.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to write to `writer`,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        let text = self.render();
+        track!(writer
+            .write_all(text.as_bytes())
+            .map_err(error::from_io_error))?;
+        track!(writer.flush().map_err(error::from_io_error))?;
+        Ok(())
+    }
+
+    fn lock_series(&self) -> MutexGuard<'_, BTreeMap<(String, String), Series>> {
+        self.series.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+impl Reporter for SpanMetrics {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        self.record(spans);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Series {
+    count: u64,
+    errors: u64,
+    sum: f64,
+
+    /// Non-cumulative counts of the buckets (excluding `+Inf`).
+    bucket_counts: Vec<u64>,
+}
+impl Series {
+    fn new(buckets: usize) -> Self {
+        Series {
+            count: 0,
+            errors: 0,
+            sum: 0.0,
+            bucket_counts: vec![0; buckets],
+        }
+    }
+
+    fn observe(&mut self, buckets: &[f64], duration: f64, is_error: bool) {
+        self.count += 1;
+        if is_error {
+            self.errors += 1;
+        }
+        self.sum += duration;
+        if let Some(i) = buckets.iter().position(|b| duration <= *b) {
+            self.bucket_counts[i] += 1;
+        }
+    }
+}
+
+fn labels((service, operation): &(String, String)) -> String {
+    format!(
+        "service=\"{}\",operation=\"{}\"",
+        escape_label_value(service),
+        escape_label_value(operation)
+    )
+}
+
+fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::SpanContextState;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use rustracing::tag::StdTag;
+    use std::time::{Duration, SystemTime};
+    use trackable::result::TestResult;
+
+    fn finished_spans() -> Result<Vec<FinishedSpan>> {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        let t0 = SystemTime::now();
+        for (millis, error) in [(3, false), (40, true), (2000, false)] {
+            let mut span = tracer.span("get \"x\"").start_time(t0).start();
+            if error {
+                span.set_tag(StdTag::error);
+            }
+            span.set_finish_time(|| t0 + Duration::from_millis(millis));
+        }
+        let state: SpanContextState =
+            track!("6309ab92c95468edea0dc1a9772ae2dc:409423a204bc17a8:0:0".parse())?;
+        tracer.span("unsampled").start_with_state(state);
+        Ok(span_rx.try_iter().collect())
+    }
+
+    #[test]
+    fn span_metrics_works() -> TestResult {
+        let spans = track!(finished_spans())?;
+        let mut metrics = SpanMetrics::new("metrics_test");
+        metrics.set_buckets(vec![1.0, 0.01, 0.1]);
+        metrics.record(&spans);
+
+        let labels = r#"service="metrics_test",operation="get \"x\"""#;
+        let expected = [
+            "# TYPE spans_total counter".to_owned(),
+            format!("spans_total{{{}}} 3", labels),
+            format!("span_errors_total{{{}}} 1", labels),
+            "# TYPE span_duration_seconds histogram".to_owned(),
+            format!("span_duration_seconds_bucket{{{},le=\"0.01\"}} 1", labels),
+            format!("span_duration_seconds_bucket{{{},le=\"0.1\"}} 2", labels),
+            format!("span_duration_seconds_bucket{{{},le=\"1\"}} 2", labels),
+            format!("span_duration_seconds_bucket{{{},le=\"+Inf\"}} 3", labels),
+            format!("span_duration_seconds_sum{{{}}} 2.043", labels),
+            format!("span_duration_seconds_count{{{}}} 3", labels),
+        ];
+        let mut buf = Vec::new();
+        track!(metrics.write_to(&mut buf))?;
+        let text = track_any_err!(String::from_utf8(buf))?;
+        for line in &expected {
+            assert!(text.lines().any(|l| l == line), "missing: {}", line);
+        }
+        assert!(!text.contains("unsampled"));
+        Ok(())
+    }
+
+    #[test]
+    fn unsampled_spans_and_services_work() -> TestResult {
+        let spans = track!(finished_spans())?;
+        let mut metrics = SpanMetrics::new("metrics_test");
+        metrics.set_include_unsampled(true);
+        metrics.record_service_spans("other", &spans[3..]);
+        track!(metrics.report(&spans))?;
+
+        let text = metrics.render();
+        assert!(text.contains(r#"spans_total{service="metrics_test",operation="unsampled"} 1"#));
+        assert!(text.contains(r#"spans_total{service="other",operation="unsampled"} 1"#));
+        assert!(text.contains(r#"spans_total{service="metrics_test",operation="get \"x\""} 3"#));
+        Ok(())
+    }
+}
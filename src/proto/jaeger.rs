@@ -0,0 +1,324 @@
+//! Protobuf components defined in [model.proto] and [collector.proto] (i.e., `jaeger.api_v2`).
+//!
+//! [model.proto]: https://github.com/jaegertracing/jaeger-idl/blob/main/proto/api_v2/model.proto
+//! [collector.proto]: https://github.com/jaegertracing/jaeger-idl/blob/main/proto/api_v2/collector.proto
+use super::{span_id, trace_id};
+use crate::thrift::jaeger as thrift;
+use prost_types::{Duration, Timestamp};
+
+/// `ValueType` denotes the type of a `KeyValue`'s value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, prost::Enumeration)]
+#[repr(i32)]
+#[allow(missing_docs)]
+pub enum ValueType {
+    String = 0,
+    Bool = 1,
+    Int64 = 2,
+    Float64 = 3,
+    Binary = 4,
+}
+
+/// `KeyValue` is a basic strongly typed key/value pair.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct KeyValue {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(enumeration = "ValueType", tag = "2")]
+    pub v_type: i32,
+    #[prost(string, tag = "3")]
+    pub v_str: String,
+    #[prost(bool, tag = "4")]
+    pub v_bool: bool,
+    #[prost(int64, tag = "5")]
+    pub v_int64: i64,
+    #[prost(double, tag = "6")]
+    pub v_float64: f64,
+    #[prost(bytes = "vec", tag = "7")]
+    pub v_binary: Vec<u8>,
+}
+impl From<thrift::Tag> for KeyValue {
+    fn from(f: thrift::Tag) -> Self {
+        let mut kv = KeyValue {
+            key: f.key().to_owned(),
+            ..KeyValue::default()
+        };
+        match f {
+            thrift::Tag::String { value, .. } => {
+                kv.v_type = ValueType::String as i32;
+                kv.v_str = value;
+            }
+            thrift::Tag::Double { value, .. } => {
+                kv.v_type = ValueType::Float64 as i32;
+                kv.v_float64 = value;
+            }
+            thrift::Tag::Bool { value, .. } => {
+                kv.v_type = ValueType::Bool as i32;
+                kv.v_bool = value;
+            }
+            thrift::Tag::Long { value, .. } => {
+                kv.v_type = ValueType::Int64 as i32;
+                kv.v_int64 = value;
+            }
+            thrift::Tag::Binary { value, .. } => {
+                kv.v_type = ValueType::Binary as i32;
+                kv.v_binary = value;
+            }
+        }
+        kv
+    }
+}
+
+/// `Log` is a timed event with an arbitrary set of fields.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct Log {
+    #[prost(message, optional, tag = "1")]
+    pub timestamp: Option<Timestamp>,
+    #[prost(message, repeated, tag = "2")]
+    pub fields: Vec<KeyValue>,
+}
+impl From<thrift::Log> for Log {
+    fn from(f: thrift::Log) -> Self {
+        Log {
+            timestamp: Some(timestamp(f.timestamp)),
+            fields: f.fields.into_iter().map(From::from).collect(),
+        }
+    }
+}
+
+/// Span reference type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, prost::Enumeration)]
+#[repr(i32)]
+#[allow(missing_docs)]
+pub enum SpanRefType {
+    ChildOf = 0,
+    FollowsFrom = 1,
+}
+
+/// `SpanRef` describes causal relationship of the current span to another span (e.g. 'child-of')
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct SpanRef {
+    #[prost(bytes = "vec", tag = "1")]
+    pub trace_id: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub span_id: Vec<u8>,
+    #[prost(enumeration = "SpanRefType", tag = "3")]
+    pub ref_type: i32,
+}
+impl From<thrift::SpanRef> for SpanRef {
+    fn from(f: thrift::SpanRef) -> Self {
+        let ref_type = match f.kind {
+            thrift::SpanRefKind::ChildOf => SpanRefType::ChildOf,
+            thrift::SpanRefKind::FollowsFrom => SpanRefType::FollowsFrom,
+        };
+        SpanRef {
+            trace_id: trace_id(f.trace_id_high, f.trace_id_low),
+            span_id: span_id(f.span_id),
+            ref_type: ref_type as i32,
+        }
+    }
+}
+
+/// `Process` describes the traced process/service that emits spans.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct Process {
+    #[prost(string, tag = "1")]
+    pub service_name: String,
+    #[prost(message, repeated, tag = "2")]
+    pub tags: Vec<KeyValue>,
+}
+impl From<thrift::Process> for Process {
+    fn from(f: thrift::Process) -> Self {
+        Process {
+            service_name: f.service_name,
+            tags: f.tags.into_iter().map(From::from).collect(),
+        }
+    }
+}
+
+/// `Span` represents a named unit of work performed by a service.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Span {
+    /// 16 bytes trace identifier (big endian).
+    #[prost(bytes = "vec", tag = "1")]
+    pub trace_id: Vec<u8>,
+
+    /// 8 bytes span identifier (big endian).
+    #[prost(bytes = "vec", tag = "2")]
+    pub span_id: Vec<u8>,
+
+    /// The name of operation.
+    #[prost(string, tag = "3")]
+    pub operation_name: String,
+
+    /// Causal references to other spans.
+    ///
+    /// Unlike jaeger.thrift, the parent span is also represented as a `ChildOf` reference.
+    #[prost(message, repeated, tag = "4")]
+    pub references: Vec<SpanRef>,
+
+    /// A bit field used to propagate sampling decisions.
+    ///
+    /// `1` signifies a SAMPLED span, `2` signifies a DEBUG span.
+    #[prost(uint32, tag = "5")]
+    pub flags: u32,
+
+    /// Start time of this span.
+    #[prost(message, optional, tag = "6")]
+    pub start_time: Option<Timestamp>,
+
+    /// Duration of this span.
+    #[prost(message, optional, tag = "7")]
+    pub duration: Option<Duration>,
+
+    /// Tag list.
+    #[prost(message, repeated, tag = "8")]
+    pub tags: Vec<KeyValue>,
+
+    /// Log list.
+    #[prost(message, repeated, tag = "9")]
+    pub logs: Vec<Log>,
+
+    /// The process which emitted this span.
+    ///
+    /// This is usually omitted since the process is specified in `Batch`.
+    #[prost(message, optional, tag = "10")]
+    pub process: Option<Process>,
+
+    /// The identifier of the process (used by the storage backends).
+    #[prost(string, tag = "11")]
+    pub process_id: String,
+
+    /// Warnings detected while processing this span.
+    #[prost(string, repeated, tag = "12")]
+    pub warnings: Vec<String>,
+}
+impl From<thrift::Span> for Span {
+    fn from(f: thrift::Span) -> Self {
+        let mut references = f
+            .references
+            .into_iter()
+            .map(SpanRef::from)
+            .collect::<Vec<_>>();
+        if f.parent_span_id != 0 {
+            let parent_span_id = span_id(f.parent_span_id);
+            if !references.iter().any(|r| r.span_id == parent_span_id) {
+                references.insert(
+                    0,
+                    SpanRef {
+                        trace_id: trace_id(f.trace_id_high, f.trace_id_low),
+                        span_id: parent_span_id,
+                        ref_type: SpanRefType::ChildOf as i32,
+                    },
+                );
+            }
+        }
+        Span {
+            trace_id: trace_id(f.trace_id_high, f.trace_id_low),
+            span_id: span_id(f.span_id),
+            operation_name: f.operation_name,
+            references,
+            flags: f.flags as u32,
+            start_time: Some(timestamp(f.start_time)),
+            duration: Some(Duration {
+                seconds: f.duration.div_euclid(1_000_000),
+                nanos: (f.duration.rem_euclid(1_000_000) * 1000) as i32,
+            }),
+            tags: f.tags.into_iter().map(From::from).collect(),
+            logs: f.logs.into_iter().map(From::from).collect(),
+            process: None,
+            process_id: String::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// `Batch` is a collection of spans reported out of process.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct Batch {
+    #[prost(message, repeated, tag = "1")]
+    pub spans: Vec<Span>,
+    #[prost(message, optional, tag = "2")]
+    pub process: Option<Process>,
+}
+
+/// The request of the `jaeger.api_v2.CollectorService/PostSpans` method.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct PostSpansRequest {
+    #[prost(message, optional, tag = "1")]
+    pub batch: Option<Batch>,
+}
+
+/// The response of the `jaeger.api_v2.CollectorService/PostSpans` method.
+#[derive(Clone, Copy, PartialEq, prost::Message)]
+pub struct PostSpansResponse {}
+
+/// Converts microseconds since the UNIX epoch to a `Timestamp`.
+fn timestamp(micros: i64) -> Timestamp {
+    Timestamp {
+        seconds: micros.div_euclid(1_000_000),
+        nanos: (micros.rem_euclid(1_000_000) * 1000) as i32,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::{SpanContextStateBuilder, TraceId};
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+
+    #[test]
+    fn span_conversion_works() {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        {
+            let parent = tracer.span("parent").start_with_state(
+                SpanContextStateBuilder::new()
+                    .trace_id(TraceId {
+                        high: 1,
+                        low: 0x1234,
+                    })
+                    .finish(),
+            );
+            let _child = tracer
+                .span("child")
+                .child_of(&parent)
+                .tag(rustracing::tag::Tag::new("foo", 10))
+                .start();
+        }
+        let child = thrift::Span::from(&span_rx.try_recv().unwrap());
+        let span = Span::from(child.clone());
+        assert_eq!(span.operation_name, "child");
+        assert_eq!(
+            span.trace_id,
+            [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0x12, 0x34]
+        );
+        assert_eq!(span.span_id, (child.span_id as u64).to_be_bytes());
+        assert_eq!(span.references.len(), 1);
+        assert_eq!(
+            span.references[0].span_id,
+            (child.parent_span_id as u64).to_be_bytes()
+        );
+        assert_eq!(span.references[0].ref_type(), SpanRefType::ChildOf);
+        assert_eq!(
+            span.tags,
+            vec![KeyValue {
+                key: "foo".to_owned(),
+                v_type: ValueType::Int64 as i32,
+                v_int64: 10,
+                ..KeyValue::default()
+            }]
+        );
+
+        let start_time = span.start_time.unwrap();
+        assert_eq!(
+            start_time.seconds * 1_000_000 + i64::from(start_time.nanos) / 1000,
+            child.start_time
+        );
+    }
+}
@@ -0,0 +1,20 @@
+//! Protobuf messages for Jaeger and OpenTelemetry.
+#[cfg(feature = "grpc")]
+pub mod jaeger;
+#[cfg(feature = "otlp")]
+pub mod otlp;
+
+/// Returns the 16 bytes (big-endian) representation of the trace id.
+#[cfg(any(feature = "grpc", feature = "otlp"))]
+fn trace_id(high: i64, low: i64) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(16);
+    bytes.extend_from_slice(&high.to_be_bytes());
+    bytes.extend_from_slice(&low.to_be_bytes());
+    bytes
+}
+
+/// Returns the 8 bytes (big-endian) representation of the span id.
+#[cfg(any(feature = "grpc", feature = "otlp"))]
+fn span_id(id: i64) -> Vec<u8> {
+    id.to_be_bytes().to_vec()
+}
@@ -0,0 +1,460 @@
+//! Protobuf components defined in the [OpenTelemetry protocol] (i.e., `opentelemetry.proto.trace.v1`).
+//!
+//! Only the subset needed to represent the spans of this crate is defined.
+//!
+//! [OpenTelemetry protocol]: https://github.com/open-telemetry/opentelemetry-proto
+use super::{span_id, trace_id};
+use crate::thrift::jaeger;
+
+/// The name of the attribute which denotes the service name of a resource.
+pub const SERVICE_NAME_KEY: &str = "service.name";
+
+/// The name of the link attribute which denotes the kind of the original reference.
+pub const REF_TYPE_KEY: &str = "opentracing.ref_type";
+
+/// The request of the `opentelemetry.proto.collector.trace.v1.TraceService/Export` method.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct ExportTraceServiceRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub resource_spans: Vec<ResourceSpans>,
+}
+
+/// A collection of `ScopeSpans` from a resource.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct ResourceSpans {
+    #[prost(message, optional, tag = "1")]
+    pub resource: Option<Resource>,
+    #[prost(message, repeated, tag = "2")]
+    pub scope_spans: Vec<ScopeSpans>,
+    #[prost(string, tag = "3")]
+    pub schema_url: String,
+}
+
+/// The entity producing telemetry (i.e., the traced process/service).
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct Resource {
+    #[prost(message, repeated, tag = "1")]
+    pub attributes: Vec<KeyValue>,
+    #[prost(uint32, tag = "2")]
+    pub dropped_attributes_count: u32,
+}
+impl From<jaeger::Process> for Resource {
+    fn from(f: jaeger::Process) -> Self {
+        let mut attributes = vec![KeyValue::new(SERVICE_NAME_KEY, f.service_name.into())];
+        attributes.extend(f.tags.into_iter().map(KeyValue::from));
+        Resource {
+            attributes,
+            dropped_attributes_count: 0,
+        }
+    }
+}
+
+/// A collection of spans produced by an instrumentation scope.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct ScopeSpans {
+    #[prost(message, optional, tag = "1")]
+    pub scope: Option<InstrumentationScope>,
+    #[prost(message, repeated, tag = "2")]
+    pub spans: Vec<Span>,
+    #[prost(string, tag = "3")]
+    pub schema_url: String,
+}
+
+/// The instrumentation library which produced spans.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct InstrumentationScope {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub version: String,
+    #[prost(message, repeated, tag = "3")]
+    pub attributes: Vec<KeyValue>,
+    #[prost(uint32, tag = "4")]
+    pub dropped_attributes_count: u32,
+}
+
+/// Span kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, prost::Enumeration)]
+#[repr(i32)]
+#[allow(missing_docs)]
+pub enum SpanKind {
+    Unspecified = 0,
+    Internal = 1,
+    Server = 2,
+    Client = 3,
+    Producer = 4,
+    Consumer = 5,
+}
+impl SpanKind {
+    /// Returns the kind corresponding to the value of the `span.kind` tag.
+    pub fn from_tag_value(value: &str) -> Self {
+        match value {
+            "server" => SpanKind::Server,
+            "client" => SpanKind::Client,
+            "producer" => SpanKind::Producer,
+            "consumer" => SpanKind::Consumer,
+            _ => SpanKind::Internal,
+        }
+    }
+}
+
+/// `Span` represents a named unit of work performed by a service.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Span {
+    /// 16 bytes trace identifier (big endian).
+    #[prost(bytes = "vec", tag = "1")]
+    pub trace_id: Vec<u8>,
+
+    /// 8 bytes span identifier (big endian).
+    #[prost(bytes = "vec", tag = "2")]
+    pub span_id: Vec<u8>,
+
+    /// W3C trace state.
+    #[prost(string, tag = "3")]
+    pub trace_state: String,
+
+    /// 8 bytes identifier of the parent span; empty if this is a root span.
+    #[prost(bytes = "vec", tag = "4")]
+    pub parent_span_id: Vec<u8>,
+
+    /// W3C trace flags (`1` signifies a SAMPLED span).
+    #[prost(fixed32, tag = "16")]
+    pub flags: u32,
+
+    /// The name of operation.
+    #[prost(string, tag = "5")]
+    pub name: String,
+
+    /// The kind of this span (e.g., `SpanKind::Client`).
+    #[prost(enumeration = "SpanKind", tag = "6")]
+    pub kind: i32,
+
+    /// Start time of this span in nanoseconds since the UNIX epoch.
+    #[prost(fixed64, tag = "7")]
+    pub start_time_unix_nano: u64,
+
+    /// Finish time of this span in nanoseconds since the UNIX epoch.
+    #[prost(fixed64, tag = "8")]
+    pub end_time_unix_nano: u64,
+
+    /// Attribute (i.e., tag) list.
+    #[prost(message, repeated, tag = "9")]
+    pub attributes: Vec<KeyValue>,
+
+    /// The number of the dropped attributes.
+    #[prost(uint32, tag = "10")]
+    pub dropped_attributes_count: u32,
+
+    /// Event (i.e., log) list.
+    #[prost(message, repeated, tag = "11")]
+    pub events: Vec<Event>,
+
+    /// The number of the dropped events.
+    #[prost(uint32, tag = "12")]
+    pub dropped_events_count: u32,
+
+    /// Links to other spans (i.e., the references other than the parent).
+    #[prost(message, repeated, tag = "13")]
+    pub links: Vec<Link>,
+
+    /// The number of the dropped links.
+    #[prost(uint32, tag = "14")]
+    pub dropped_links_count: u32,
+
+    /// The status of this span.
+    #[prost(message, optional, tag = "15")]
+    pub status: Option<Status>,
+}
+impl From<jaeger::Span> for Span {
+    fn from(f: jaeger::Span) -> Self {
+        let mut parent_span_id = Vec::new();
+        let mut links = Vec::new();
+        for r in f.references {
+            if parent_span_id.is_empty() && r.span_id == f.parent_span_id {
+                parent_span_id = span_id(r.span_id);
+                continue;
+            }
+            let ref_type = match r.kind {
+                jaeger::SpanRefKind::ChildOf => "child_of",
+                jaeger::SpanRefKind::FollowsFrom => "follows_from",
+            };
+            links.push(Link {
+                trace_id: trace_id(r.trace_id_high, r.trace_id_low),
+                span_id: span_id(r.span_id),
+                trace_state: String::new(),
+                attributes: vec![KeyValue::new(REF_TYPE_KEY, ref_type.into())],
+                dropped_attributes_count: 0,
+                flags: 0,
+            });
+        }
+        if parent_span_id.is_empty() && f.parent_span_id != 0 {
+            parent_span_id = span_id(f.parent_span_id);
+        }
+
+        let mut kind = SpanKind::Internal;
+        let mut status = None;
+        let mut attributes = Vec::with_capacity(f.tags.len());
+        for tag in f.tags {
+            match tag {
+                jaeger::Tag::String { ref key, ref value } if key == "span.kind" => {
+                    kind = SpanKind::from_tag_value(value);
+                }
+                jaeger::Tag::Bool { ref key, value } if key == "error" && value => {
+                    status = Some(Status {
+                        message: String::new(),
+                        code: StatusCode::Error as i32,
+                    });
+                    attributes.push(KeyValue::from(tag));
+                }
+                _ => attributes.push(KeyValue::from(tag)),
+            }
+        }
+
+        let start_time_unix_nano = micros_to_nanos(f.start_time);
+        Span {
+            trace_id: trace_id(f.trace_id_high, f.trace_id_low),
+            span_id: span_id(f.span_id),
+            trace_state: String::new(),
+            parent_span_id,
+            flags: (f.flags & 1) as u32,
+            name: f.operation_name,
+            kind: kind as i32,
+            start_time_unix_nano,
+            end_time_unix_nano: start_time_unix_nano + micros_to_nanos(f.duration),
+            attributes,
+            dropped_attributes_count: 0,
+            events: f.logs.into_iter().map(Event::from).collect(),
+            dropped_events_count: 0,
+            links,
+            dropped_links_count: 0,
+            status,
+        }
+    }
+}
+
+/// `Event` is a timed event with an arbitrary set of attributes (i.e., a log of a span).
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct Event {
+    #[prost(fixed64, tag = "1")]
+    pub time_unix_nano: u64,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(message, repeated, tag = "3")]
+    pub attributes: Vec<KeyValue>,
+    #[prost(uint32, tag = "4")]
+    pub dropped_attributes_count: u32,
+}
+impl From<jaeger::Log> for Event {
+    /// The value of the `event` field is used as the name of the event if it exists.
+    fn from(f: jaeger::Log) -> Self {
+        let mut name = None;
+        let mut attributes = Vec::with_capacity(f.fields.len());
+        for field in f.fields {
+            match field {
+                jaeger::Tag::String { key, value } if key == "event" && name.is_none() => {
+                    name = Some(value);
+                }
+                _ => attributes.push(KeyValue::from(field)),
+            }
+        }
+        Event {
+            time_unix_nano: micros_to_nanos(f.timestamp),
+            name: name.unwrap_or_else(|| "log".to_owned()),
+            attributes,
+            dropped_attributes_count: 0,
+        }
+    }
+}
+
+/// A link from a span to another span.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct Link {
+    #[prost(bytes = "vec", tag = "1")]
+    pub trace_id: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub span_id: Vec<u8>,
+    #[prost(string, tag = "3")]
+    pub trace_state: String,
+    #[prost(message, repeated, tag = "4")]
+    pub attributes: Vec<KeyValue>,
+    #[prost(uint32, tag = "5")]
+    pub dropped_attributes_count: u32,
+    #[prost(fixed32, tag = "6")]
+    pub flags: u32,
+}
+
+/// Status code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, prost::Enumeration)]
+#[repr(i32)]
+#[allow(missing_docs)]
+pub enum StatusCode {
+    Unset = 0,
+    Ok = 1,
+    Error = 2,
+}
+
+/// The status of a span.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct Status {
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(enumeration = "StatusCode", tag = "3")]
+    pub code: i32,
+}
+
+/// `KeyValue` is a key/value pair used for attributes.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct KeyValue {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(message, optional, tag = "2")]
+    pub value: Option<AnyValue>,
+}
+impl KeyValue {
+    /// Makes a new `KeyValue` instance.
+    pub fn new(key: &str, value: any_value::Value) -> Self {
+        KeyValue {
+            key: key.to_owned(),
+            value: Some(AnyValue { value: Some(value) }),
+        }
+    }
+}
+impl From<jaeger::Tag> for KeyValue {
+    fn from(f: jaeger::Tag) -> Self {
+        use self::any_value::Value;
+        let key = f.key().to_owned();
+        let value = match f {
+            jaeger::Tag::String { value, .. } => Value::StringValue(value),
+            jaeger::Tag::Double { value, .. } => Value::DoubleValue(value),
+            jaeger::Tag::Bool { value, .. } => Value::BoolValue(value),
+            jaeger::Tag::Long { value, .. } => Value::IntValue(value),
+            jaeger::Tag::Binary { value, .. } => Value::BytesValue(value),
+        };
+        KeyValue {
+            key,
+            value: Some(AnyValue { value: Some(value) }),
+        }
+    }
+}
+
+/// A value of an attribute.
+#[derive(Clone, PartialEq, prost::Message)]
+#[allow(missing_docs)]
+pub struct AnyValue {
+    #[prost(oneof = "any_value::Value", tags = "1, 2, 3, 4, 7")]
+    pub value: Option<any_value::Value>,
+}
+
+/// The variants of `AnyValue`.
+pub mod any_value {
+    /// A value of an attribute.
+    ///
+    /// Arrays and key/value lists are not supported.
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    #[allow(missing_docs)]
+    pub enum Value {
+        #[prost(string, tag = "1")]
+        StringValue(String),
+        #[prost(bool, tag = "2")]
+        BoolValue(bool),
+        #[prost(int64, tag = "3")]
+        IntValue(i64),
+        #[prost(double, tag = "4")]
+        DoubleValue(f64),
+        #[prost(bytes, tag = "7")]
+        BytesValue(Vec<u8>),
+    }
+    impl From<String> for Value {
+        fn from(f: String) -> Self {
+            Value::StringValue(f)
+        }
+    }
+    impl<'a> From<&'a str> for Value {
+        fn from(f: &'a str) -> Self {
+            Value::StringValue(f.to_owned())
+        }
+    }
+}
+
+fn micros_to_nanos(micros: i64) -> u64 {
+    micros.max(0) as u64 * 1000
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::{SpanContextStateBuilder, TraceId};
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use rustracing::tag::{StdTag, Tag};
+
+    #[test]
+    fn span_conversion_works() {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        let state = SpanContextStateBuilder::new()
+            .trace_id(TraceId { high: 1, low: 2 })
+            .debug_id("foo".to_owned())
+            .finish();
+        {
+            let parent = tracer.span("parent").start_with_state(state);
+            let mut child = tracer
+                .span("child")
+                .child_of(&parent)
+                .tag(StdTag::span_kind("client"))
+                .tag(StdTag::error())
+                .tag(Tag::new("foo", 10))
+                .start();
+            child.log(|log| {
+                log.std().event("cache-miss");
+                log.field(rustracing::log::LogField::new("key", "bar"));
+            });
+        }
+        let child = jaeger::Span::from(&span_rx.try_recv().unwrap());
+        let parent = jaeger::Span::from(&span_rx.try_recv().unwrap());
+
+        let span = Span::from(child.clone());
+        assert_eq!(span.name, "child");
+        assert_eq!(
+            span.trace_id,
+            [0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2]
+        );
+        assert_eq!(span.parent_span_id, (parent.span_id as u64).to_be_bytes());
+        assert!(span.links.is_empty());
+        assert_eq!(span.kind(), SpanKind::Client);
+        assert_eq!(
+            span.status.as_ref().map(|s| s.code()),
+            Some(StatusCode::Error)
+        );
+        assert_eq!(
+            span.attributes
+                .iter()
+                .map(|a| &a.key[..])
+                .collect::<Vec<_>>(),
+            ["error", "foo"]
+        );
+        assert_eq!(span.events.len(), 1);
+        assert_eq!(span.events[0].name, "cache-miss");
+        assert_eq!(span.events[0].attributes[0].key, "key");
+        assert_eq!(
+            span.end_time_unix_nano - span.start_time_unix_nano,
+            child.duration as u64 * 1000
+        );
+
+        let span = Span::from(parent);
+        assert!(span.parent_span_id.is_empty());
+        assert_eq!(span.flags, 1);
+        assert_eq!(
+            span.attributes[0],
+            KeyValue::new(crate::constants::JAEGER_DEBUG_HEADER, "foo".into())
+        );
+    }
+}
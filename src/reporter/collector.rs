@@ -0,0 +1,286 @@
+use super::{BatchFormat, JaegerReporter, Reporter, ReporterStats, RetryPolicy, Spool, Transport};
+use crate::http::{self, Url};
+use crate::span::FinishedSpan;
+use crate::{ErrorKind, Result};
+use rustracing::tag::Tag;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Reporter which sends jaeger.thrift batches to the collector over HTTP (i.e., `/api/traces`).
+///
+/// This is useful in environments where no agent is available.
+/// The batching semantics (including the retry buffer and the spool) are the same as
+/// `JaegerBinaryReporter`, and a request is sent for each packet.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::JaegerHttpReporter;
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// let spans = span_rx.try_iter().collect::<Vec<_>>();
+///
+/// let mut reporter =
+///     JaegerHttpReporter::new("sample_service", "http://jaeger-collector:14268/api/traces").unwrap();
+/// reporter.set_header("Authorization", "Bearer secret").unwrap();
+/// reporter.report(&spans).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct JaegerHttpReporter(pub(super) JaegerReporter<HttpTransport>);
+impl JaegerHttpReporter {
+    /// Makes a new `JaegerHttpReporter` instance which sends batches to `collector_url`
+    /// (e.g., `"http://127.0.0.1:14268/api/traces"`).
+    ///
+    /// # Errors
+    ///
+    /// If `collector_url` is not a valid `http://` URL,
+    /// this function will return an error which has the kind `ErrorKind::InvalidInput`.
+    pub fn new(service_name: &str, collector_url: &str) -> Result<Self> {
+        let transport = track!(HttpTransport::new(collector_url))?;
+        Ok(JaegerHttpReporter(JaegerReporter::new(
+            service_name,
+            BatchFormat::Collector,
+            transport,
+        )))
+    }
+
+    /// Returns a reference to the transport of this reporter.
+    pub fn transport(&self) -> &HttpTransport {
+        &self.0.transport
+    }
+
+    /// Returns `false` if it is detected that the collector is unreachable.
+    pub fn is_collector_healthy(&self) -> bool {
+        self.0.transport.is_agent_healthy()
+    }
+
+    /// Returns the snapshot of the statistics of this reporter.
+    pub fn stats(&self) -> ReporterStats {
+        self.0.counters.snapshot()
+    }
+
+    /// Adds the HTTP header `name: value` to each request (e.g., for authentication).
+    ///
+    /// # Errors
+    ///
+    /// If `name` is not a valid header name, `name` is one of the headers written by the transport itself
+    /// (i.e., `Host`, `Content-Length`, `Connection` and `Transfer-Encoding`) or `value` contains CR, LF or NUL,
+    /// this method will return an error which has the kind `ErrorKind::InvalidInput`.
+    pub fn set_header(&mut self, name: &str, value: &str) -> Result<()> {
+        track!(self.0.transport.set_header(name, value))
+    }
+
+    /// Sets the timeout of each of connecting, sending a request and receiving the response.
+    ///
+    /// The default value is `Duration::from_secs(5)`.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.0.transport.set_timeout(timeout);
+    }
+
+    /// Sets the maximum size of a request body.
+    ///
+    /// Spans reported at once are split into multiple requests if their encoded size exceeds this.
+    ///
+    /// The default value is `65000`.
+    pub fn set_max_packet_size(&mut self, size: usize) {
+        self.0.set_max_packet_size(size);
+    }
+
+    /// Sets the policy for retrying failed requests.
+    ///
+    /// By default, failed requests are not retried.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.0.set_retry_policy(policy);
+    }
+
+    /// Sets the maximum number of request bodies kept in the retry buffer.
+    ///
+    /// See `JaegerBinaryReporter::set_retry_buffer_size` for the details.
+    ///
+    /// The default value is `0` (i.e., the retry buffer is disabled).
+    pub fn set_retry_buffer_size(&mut self, size: usize) {
+        self.0.set_retry_buffer_size(size);
+    }
+
+    /// Sets the spool where request bodies that could not be sent are stored.
+    ///
+    /// See `JaegerBinaryReporter::set_spool` for the details.
+    pub fn set_spool(&mut self, spool: Spool) {
+        self.0.set_spool(spool);
+    }
+
+    /// Adds `tag` to this service.
+    pub fn add_service_tag(&mut self, tag: Tag) {
+        self.0.add_service_tag(tag);
+    }
+
+    /// Reports `spans`.
+    ///
+    /// The spans are split into multiple requests if they do not fit in a request body.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to encode `spans` to the thrift binary format (i.e., a bug of this crate),
+    /// this method will return an error which has the kind `ErrorKind::InvalidInput`.
+    ///
+    /// If some of `spans` are too large to fit in a request body even alone,
+    /// they are dropped and this method will return an error which has the kind `ErrorKind::InvalidInput`
+    /// after sending the remaining spans.
+    ///
+    /// If it fails to send a request or the collector responds with a non-2xx status,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    /// In that case, the unsent bodies are kept in the retry buffer or the spool if they are enabled.
+    pub fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(self.0.report(spans))
+    }
+}
+impl Reporter for JaegerHttpReporter {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(self.0.report(spans))
+    }
+}
+
+/// Transport which POSTs packets to an HTTP endpoint.
+#[derive(Debug)]
+pub struct HttpTransport {
+    url: Url,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+    healthy: AtomicBool,
+}
+impl HttpTransport {
+    /// Makes a new `HttpTransport` instance which POSTs packets to `url`
+    /// with `Content-Type: application/x-thrift`.
+    ///
+    /// # Errors
+    ///
+    /// If `url` is not a valid `http://` URL,
+    /// this function will return an error which has the kind `ErrorKind::InvalidInput`.
+    pub fn new(url: &str) -> Result<Self> {
+        let url = track!(Url::parse(url))?;
+        Ok(HttpTransport {
+            url,
+            headers: vec![("Content-Type".to_owned(), "application/x-thrift".to_owned())],
+            timeout: Duration::from_secs(5),
+            healthy: AtomicBool::new(true),
+        })
+    }
+
+    /// Adds the HTTP header `name: value` to each request.
+    ///
+    /// If a header with the same name has already been set, it is replaced.
+    ///
+    /// # Errors
+    ///
+    /// If `name` is not a valid header name, `name` is one of the headers written by the transport itself
+    /// (i.e., `Host`, `Content-Length`, `Connection` and `Transfer-Encoding`) or `value` contains CR, LF or NUL,
+    /// this method will return an error which has the kind `ErrorKind::InvalidInput`.
+    pub fn set_header(&mut self, name: &str, value: &str) -> Result<()> {
+        track!(http::validate_header(name, value))?;
+        self.replace_header(name, value);
+        Ok(())
+    }
+
+    /// Sets the `Content-Type` header (`content_type` is a constant of this crate).
+    pub(crate) fn set_content_type(&mut self, content_type: &str) {
+        self.replace_header("Content-Type", content_type);
+    }
+
+    fn replace_header(&mut self, name: &str, value: &str) {
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        self.headers.push((name.to_owned(), value.to_owned()));
+    }
+
+    /// Sets the timeout of each of connecting, sending a request and receiving the response.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+}
+impl Transport for HttpTransport {
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        let response = match http::post(&self.url, &self.headers, packet, self.timeout) {
+            Ok(response) => response,
+            Err(e) => {
+                if super::is_agent_unreachable(&e) {
+                    self.healthy.store(false, Ordering::Relaxed);
+                }
+                return Err(track!(e));
+            }
+        };
+        self.healthy.store(true, Ordering::Relaxed);
+        track_assert!(
+            response.is_success(),
+            ErrorKind::Other,
+            "Unexpected HTTP status: status={}, body={:?}",
+            response.status,
+            String::from_utf8_lossy(&response.body)
+        );
+        Ok(())
+    }
+
+    fn is_agent_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::test_server;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use thrift_codec::data::{Data, Struct};
+    use thrift_codec::BinaryDecode;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn http_reporter_works() -> TestResult {
+        let (addr, rx) = track!(test_server::start(202))?;
+        let url = format!("http://{}/api/traces", addr);
+        let mut reporter = track!(JaegerHttpReporter::new("http_test", &url))?;
+        track!(reporter.set_header("Authorization", "Bearer foo"))?;
+        assert!(reporter
+            .set_header("Authorization", "foo\r\nX-Bar: baz")
+            .is_err());
+        reporter.set_timeout(Duration::from_secs(5));
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        tracer.span("bar").start();
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let request = track_any_err!(rx.recv_timeout(Duration::from_secs(5)))?;
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/api/traces");
+        assert_eq!(request.header("content-type"), Some("application/x-thrift"));
+        assert_eq!(request.header("authorization"), Some("Bearer foo"));
+
+        let batch = track_any_err!(Struct::binary_decode(&mut &request.body[..]))?;
+        match batch.fields()[1].data() {
+            Data::List(spans) => assert_eq!(spans.len(), 2),
+            data => panic!("unexpected data: {:?}", data),
+        }
+        assert_eq!(reporter.stats().spans_sent, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn error_status_is_reported() -> TestResult {
+        let (addr, _rx) = track!(test_server::start(500))?;
+        let url = format!("http://{}/api/traces", addr);
+        let reporter = track!(JaegerHttpReporter::new("http_test", &url))?;
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        assert!(reporter
+            .report(&span_rx.try_iter().collect::<Vec<_>>())
+            .is_err());
+        assert_eq!(reporter.stats().send_failures, 1);
+        Ok(())
+    }
+}
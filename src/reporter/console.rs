@@ -0,0 +1,415 @@
+use super::stats::{ReporterCounters, ReporterStats};
+use super::Reporter;
+use crate::constants;
+use crate::error;
+use crate::json;
+use crate::span::FinishedSpan;
+use crate::thrift::jaeger;
+use crate::Result;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Reporter which prints spans in a human-readable form (e.g., to the terminal).
+///
+/// This is useful for local development where no Jaeger is running.
+///
+/// By default, the spans reported at once are printed as an indented tree per trace:
+///
+/// ```text
+/// trace 0000000000000abc
+///   parent 12.345ms debug_id=foo
+///     tags: http.method=GET
+///     log +0.120ms: event=cache-miss
+///     child 1.000ms
+/// ```
+///
+/// In the compact mode, each span is printed in a line.
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::ConsoleReporter;
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// let spans = span_rx.try_iter().collect::<Vec<_>>();
+///
+/// let mut reporter = ConsoleReporter::with_writer(Vec::new());
+/// reporter.set_colored(false);
+/// reporter.report(&spans).unwrap();
+///
+/// let output = String::from_utf8(reporter.into_writer()).unwrap();
+/// assert!(output.contains("sample_op"));
+/// ```
+#[derive(Debug)]
+pub struct ConsoleReporter<W = io::Stderr> {
+    writer: Mutex<W>,
+    colored: bool,
+    compact: bool,
+    counters: ReporterCounters,
+}
+impl ConsoleReporter {
+    /// Makes a new `ConsoleReporter` instance which prints spans to the standard error.
+    pub fn new() -> Self {
+        Self::with_writer(io::stderr())
+    }
+}
+impl Default for ConsoleReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl<W: Write> ConsoleReporter<W> {
+    /// Makes a new `ConsoleReporter` instance which prints spans to `writer`.
+    ///
+    /// Colors are enabled by default.
+    pub fn with_writer(writer: W) -> Self {
+        ConsoleReporter {
+            writer: Mutex::new(writer),
+            colored: true,
+            compact: false,
+            counters: ReporterCounters::default(),
+        }
+    }
+
+    /// Returns the writer of this reporter.
+    pub fn into_writer(self) -> W {
+        self.writer.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sets whether the output is colored by ANSI escape codes.
+    ///
+    /// The default value is `true`.
+    pub fn set_colored(&mut self, colored: bool) {
+        self.colored = colored;
+    }
+
+    /// Sets whether each span is printed in a line instead of a tree.
+    ///
+    /// The default value is `false`.
+    pub fn set_compact(&mut self, compact: bool) {
+        self.compact = compact;
+    }
+
+    /// Returns the snapshot of the statistics of this reporter.
+    pub fn stats(&self) -> ReporterStats {
+        self.counters.snapshot()
+    }
+
+    /// Reports `spans`.
+    ///
+    /// Spans whose parents are not included in `spans` are printed as roots.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to write to the writer,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        let spans = spans.iter().map(jaeger::Span::from).collect::<Vec<_>>();
+        let output = if self.compact {
+            self.format_compact(&spans)
+        } else {
+            self.format_tree(&spans)
+        };
+
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let result = writer
+            .write_all(output.as_bytes())
+            .and_then(|()| writer.flush());
+        match result {
+            Ok(()) => {
+                self.counters.record_sent(output.len(), spans.len());
+                Ok(())
+            }
+            Err(e) => {
+                self.counters.record_send_failure();
+                Err(track!(error::from_io_error(e)))
+            }
+        }
+    }
+
+    fn format_compact(&self, spans: &[jaeger::Span]) -> String {
+        let mut out = String::new();
+        for span in spans {
+            let _ = write!(
+                out,
+                "{} {} ",
+                self.paint(
+                    YELLOW,
+                    &json::trace_id(span.trace_id_high, span.trace_id_low)
+                ),
+                json::span_id(span.span_id)
+            );
+            self.format_span_header(&mut out, span);
+            let (tags, _) = split_debug_id(&span.tags);
+            for tag in tags {
+                let _ = write!(out, " {}", self.paint(DIM, &key_value(tag)));
+            }
+            if !span.logs.is_empty() {
+                let _ = write!(out, " ({} logs)", span.logs.len());
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn format_tree(&self, spans: &[jaeger::Span]) -> String {
+        // Traces are printed in the order of their first spans.
+        let mut traces: Vec<((i64, i64), Vec<usize>)> = Vec::new();
+        let mut trace_indices = HashMap::<(i64, i64), usize>::new();
+        for (i, span) in spans.iter().enumerate() {
+            let trace_id = (span.trace_id_high, span.trace_id_low);
+            match trace_indices.get(&trace_id) {
+                Some(&t) => traces[t].1.push(i),
+                None => {
+                    trace_indices.insert(trace_id, traces.len());
+                    traces.push((trace_id, vec![i]));
+                }
+            }
+        }
+
+        let mut out = String::new();
+        for ((high, low), mut members) in traces {
+            members.sort_by_key(|&i| spans[i].start_time);
+            let ids = members
+                .iter()
+                .map(|&i| spans[i].span_id)
+                .collect::<HashSet<_>>();
+            let mut roots = Vec::new();
+            let mut children = HashMap::<i64, Vec<usize>>::new();
+            for &i in &members {
+                let span = &spans[i];
+                if span.parent_span_id != span.span_id && ids.contains(&span.parent_span_id) {
+                    children.entry(span.parent_span_id).or_default().push(i);
+                } else {
+                    roots.push(i);
+                }
+            }
+
+            let _ = writeln!(
+                out,
+                "{}",
+                self.paint(YELLOW, &format!("trace {}", json::trace_id(high, low)))
+            );
+            let mut visited = HashSet::new();
+            for i in roots {
+                self.format_subtree(&mut out, spans, &children, &mut visited, i, 1);
+            }
+            // Spans in a reference cycle are not reachable from the roots.
+            for i in members {
+                self.format_subtree(&mut out, spans, &children, &mut visited, i, 1);
+            }
+        }
+        out
+    }
+
+    fn format_subtree(
+        &self,
+        out: &mut String,
+        spans: &[jaeger::Span],
+        children: &HashMap<i64, Vec<usize>>,
+        visited: &mut HashSet<usize>,
+        i: usize,
+        depth: usize,
+    ) {
+        if !visited.insert(i) {
+            return;
+        }
+        let span = &spans[i];
+        let indent = "  ".repeat(depth);
+        out.push_str(&indent);
+        self.format_span_header(out, span);
+        out.push('\n');
+
+        let (tags, _) = split_debug_id(&span.tags);
+        if !tags.is_empty() {
+            let tags = tags.iter().map(|t| key_value(t)).collect::<Vec<_>>();
+            let line = format!("tags: {}", tags.join(" "));
+            let _ = writeln!(out, "{}  {}", indent, self.paint(DIM, &line));
+        }
+        for log in &span.logs {
+            let fields = log.fields.iter().map(key_value).collect::<Vec<_>>();
+            let line = format!(
+                "log {}: {}",
+                format_offset(log.timestamp - span.start_time),
+                fields.join(" ")
+            );
+            let _ = writeln!(out, "{}  {}", indent, self.paint(DIM, &line));
+        }
+        for &child in children.get(&span.span_id).into_iter().flatten() {
+            self.format_subtree(out, spans, children, visited, child, depth + 1);
+        }
+    }
+
+    /// Writes the operation name, the duration and the debug id of `span`.
+    fn format_span_header(&self, out: &mut String, span: &jaeger::Span) {
+        let is_error = span
+            .tags
+            .iter()
+            .any(|t| matches!(t, jaeger::Tag::Bool { key, value: true } if key == "error"));
+        let name = if is_error && self.colored {
+            format!("{}{}{}{}", BOLD, RED, span.operation_name, RESET)
+        } else {
+            self.paint(BOLD, &span.operation_name)
+        };
+        let _ = write!(
+            out,
+            "{} {}",
+            name,
+            self.paint(CYAN, &format_duration(span.duration))
+        );
+        if let (_, Some(debug_id)) = split_debug_id(&span.tags) {
+            let _ = write!(out, " debug_id={}", debug_id);
+        }
+    }
+
+    fn paint(&self, color: &str, s: &str) -> String {
+        if self.colored {
+            format!("{}{}{}", color, s, RESET)
+        } else {
+            s.to_owned()
+        }
+    }
+}
+impl<W: Write> Reporter for ConsoleReporter<W> {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(ConsoleReporter::report(self, spans))
+    }
+}
+
+/// Separates the debug id tag from `tags`.
+fn split_debug_id(tags: &[jaeger::Tag]) -> (Vec<&jaeger::Tag>, Option<&str>) {
+    let mut debug_id = None;
+    let mut others = Vec::with_capacity(tags.len());
+    for tag in tags {
+        match tag {
+            jaeger::Tag::String { key, value } if key == constants::JAEGER_DEBUG_HEADER => {
+                debug_id = Some(value.as_str());
+            }
+            _ => others.push(tag),
+        }
+    }
+    (others, debug_id)
+}
+
+fn key_value(tag: &jaeger::Tag) -> String {
+    format!("{}={}", tag.key(), json::tag_value_string(tag))
+}
+
+/// Formats microseconds as milliseconds (e.g., `12.345ms`).
+fn format_duration(micros: i64) -> String {
+    format!("{}.{:03}ms", micros / 1000, (micros % 1000).abs())
+}
+
+fn format_offset(micros: i64) -> String {
+    if micros < 0 {
+        format!("-{}", format_duration(-micros))
+    } else {
+        format!("+{}", format_duration(micros))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::{SpanContextStateBuilder, TraceId};
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use rustracing::tag::Tag;
+    use trackable::result::TestResult;
+
+    fn finished_spans() -> Vec<FinishedSpan> {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        {
+            let state = SpanContextStateBuilder::new()
+                .trace_id(TraceId {
+                    high: 0,
+                    low: 0xabc,
+                })
+                .span_id(0x10)
+                .debug_id("foo".to_owned())
+                .finish();
+            let parent = tracer.span("parent").start_with_state(state);
+            let mut child = tracer
+                .span("child")
+                .child_of(&parent)
+                .tag(Tag::new("n", 1))
+                .start();
+            child.log(|log| {
+                log.std().event("cache-miss");
+            });
+            tracer.span("grandchild").child_of(&child).start();
+        }
+        span_rx.try_iter().collect()
+    }
+
+    #[test]
+    fn tree_mode_works() -> TestResult {
+        let mut reporter = ConsoleReporter::with_writer(Vec::new());
+        reporter.set_colored(false);
+        track!(reporter.report(&finished_spans()))?;
+
+        let output = track_any_err!(String::from_utf8(reporter.into_writer()))?;
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "trace 0000000000000abc");
+        assert!(lines[1].starts_with("  parent "));
+        assert!(lines[1].ends_with("ms debug_id=foo"));
+        assert!(lines[2].starts_with("    child "));
+        assert_eq!(lines[3], "      tags: n=1");
+        assert!(lines[4].starts_with("      log +"));
+        assert!(lines[4].ends_with(": event=cache-miss"));
+        assert!(lines[5].starts_with("      grandchild "));
+        Ok(())
+    }
+
+    #[test]
+    fn compact_mode_works() -> TestResult {
+        let mut reporter = ConsoleReporter::with_writer(Vec::new());
+        reporter.set_colored(false);
+        reporter.set_compact(true);
+        track!(reporter.report(&finished_spans()))?;
+
+        let output = track_any_err!(String::from_utf8(reporter.into_writer()))?;
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("0000000000000abc "));
+        assert!(lines[0].contains(" grandchild "));
+        assert!(lines[1].contains(" child "));
+        assert!(lines[1].ends_with(" n=1 (1 logs)"));
+        assert!(lines[2].starts_with("0000000000000abc 0000000000000010 parent "));
+        assert!(lines[2].ends_with("ms debug_id=foo"));
+        Ok(())
+    }
+
+    #[test]
+    fn colored_output_works() -> TestResult {
+        let reporter = ConsoleReporter::with_writer(Vec::new());
+        track!(reporter.report(&finished_spans()))?;
+
+        let output = track_any_err!(String::from_utf8(reporter.into_writer()))?;
+        assert!(output.starts_with("\x1b[33mtrace 0000000000000abc\x1b[0m\n"));
+        assert!(output.contains("\x1b[1mparent\x1b[0m \x1b[36m"));
+        Ok(())
+    }
+
+    #[test]
+    fn format_duration_works() {
+        assert_eq!(format_duration(12345), "12.345ms");
+        assert_eq!(format_duration(7), "0.007ms");
+        assert_eq!(format_offset(-1500), "-1.500ms");
+    }
+}
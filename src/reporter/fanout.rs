@@ -0,0 +1,189 @@
+use super::Reporter;
+use crate::span::FinishedSpan;
+use crate::{Error, ErrorKind, Result};
+use std::fmt;
+
+/// Reporter which forwards the same spans to multiple reporters
+/// (e.g., a `JaegerCompactReporter` and a `FileReporter`).
+///
+/// Spans are reported to all the reporters in the order they are added,
+/// even if some of them fail.
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::{
+///     ConsoleReporter, FanOutReporter, JaegerCompactReporter, Reporter,
+/// };
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// let spans = span_rx.try_iter().collect::<Vec<_>>();
+///
+/// let mut reporter = FanOutReporter::new();
+/// reporter.add_reporter(JaegerCompactReporter::new("sample_service").unwrap());
+/// reporter.add_reporter(ConsoleReporter::with_writer(Vec::new()));
+/// reporter.report(&spans).unwrap();
+/// ```
+#[derive(Default)]
+pub struct FanOutReporter {
+    reporters: Vec<Box<dyn Reporter + Send + Sync>>,
+}
+impl FanOutReporter {
+    /// Makes a new `FanOutReporter` instance which has no reporters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `reporter` to the destinations of spans.
+    pub fn add_reporter<R>(&mut self, reporter: R)
+    where
+        R: Reporter + Send + Sync + 'static,
+    {
+        self.reporters.push(Box::new(reporter));
+    }
+
+    /// Returns the number of the reporters.
+    pub fn reporter_count(&self) -> usize {
+        self.reporters.len()
+    }
+}
+impl Reporter for FanOutReporter {
+    /// Reports `spans` to all the reporters.
+    ///
+    /// # Errors
+    ///
+    /// If only one of the reporters fails, its error is returned as it is.
+    ///
+    /// If two or more reporters fail,
+    /// this method will return an error which has the kind `ErrorKind::Other`
+    /// and describes all the errors.
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        let errors = self
+            .reporters
+            .iter()
+            .enumerate()
+            .filter_map(|(i, reporter)| {
+                let e = reporter.report(spans).err()?;
+                Some((format!("[{}]", i), e))
+            })
+            .collect();
+        track!(merge_errors(errors, self.reporters.len(), "reporters"))
+    }
+}
+impl fmt::Debug for FanOutReporter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FanOutReporter")
+            .field("reporters", &self.reporters.len())
+            .finish()
+    }
+}
+
+/// Returns the only error as it is, or an error which describes all of `errors`
+/// if two or more of `total` operations (e.g., reporters) failed.
+///
+/// Each element of `errors` is a pair of the label of the failed operation and its error.
+pub(super) fn merge_errors(
+    mut errors: Vec<(String, Error)>,
+    total: usize,
+    unit: &str,
+) -> Result<()> {
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(track!(errors.remove(0).1)),
+        n => {
+            let errors = errors
+                .iter()
+                .map(|(label, e)| format!("{} {}", label, e))
+                .collect::<Vec<_>>();
+            track_panic!(
+                ErrorKind::Other,
+                "{} of {} {} failed: {}",
+                n,
+                total,
+                unit,
+                errors.join("; ")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use trackable::result::TestResult;
+
+    #[derive(Debug, Default)]
+    struct CountingReporter {
+        fail: bool,
+        spans: AtomicUsize,
+    }
+    impl Reporter for CountingReporter {
+        fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+            self.spans.fetch_add(spans.len(), Ordering::SeqCst);
+            if self.fail {
+                track_panic!(ErrorKind::InvalidInput, "down");
+            }
+            Ok(())
+        }
+    }
+
+    fn counting_reporter(fail: bool) -> Arc<CountingReporter> {
+        Arc::new(CountingReporter {
+            fail,
+            spans: AtomicUsize::new(0),
+        })
+    }
+
+    #[test]
+    fn fan_out_reporter_works() -> TestResult {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        tracer.span("bar").start();
+        let spans = span_rx.try_iter().collect::<Vec<_>>();
+
+        let first = counting_reporter(false);
+        let second = counting_reporter(false);
+        let mut reporter = FanOutReporter::new();
+        reporter.add_reporter(first.clone());
+        reporter.add_reporter(second.clone());
+        assert_eq!(reporter.reporter_count(), 2);
+
+        track!(reporter.report(&spans))?;
+        assert_eq!(first.spans.load(Ordering::SeqCst), 2);
+        assert_eq!(second.spans.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn failures_do_not_block_other_reporters() {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        let spans = span_rx.try_iter().collect::<Vec<_>>();
+
+        let failing = counting_reporter(true);
+        let healthy = counting_reporter(false);
+        let mut reporter = FanOutReporter::new();
+        reporter.add_reporter(failing.clone());
+        reporter.add_reporter(healthy.clone());
+
+        let e = reporter.report(&spans).err().unwrap();
+        assert_eq!(*e.kind(), ErrorKind::InvalidInput);
+        assert_eq!(healthy.spans.load(Ordering::SeqCst), 1);
+
+        reporter.add_reporter(failing.clone());
+        let e = reporter.report(&spans).err().unwrap();
+        assert_eq!(*e.kind(), ErrorKind::Other);
+        assert!(e.to_string().contains("2 of 3 reporters failed: [0] "));
+        assert_eq!(failing.spans.load(Ordering::SeqCst), 3);
+        assert_eq!(healthy.spans.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,367 @@
+use super::stats::{ReporterCounters, ReporterStats};
+use super::Reporter;
+use crate::error;
+use crate::json;
+use crate::span::FinishedSpan;
+use crate::thrift::jaeger;
+use crate::Result;
+use rustracing::tag::Tag;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Reporter which writes spans to a file in the JSON Lines format.
+///
+/// Each line is a JSON object which has `traceID`, `spanID`, `operationName`, `references`,
+/// `flags`, `startTime`, `duration` (in microseconds), `tags`, `logs` and `process`.
+///
+/// The file is rotated when it exceeds the maximum size or when the rotation interval elapses.
+/// A rotated file is renamed to `{path}.{unix_millis}` (and compressed to `{path}.{unix_millis}.gz`
+/// if gzip is enabled by the `gzip` feature and `set_gzip`).
+///
+/// # Examples
+///
+/// ```no_run
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::FileReporter;
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// let spans = span_rx.try_iter().collect::<Vec<_>>();
+///
+/// let mut reporter = FileReporter::new("sample_service", "/tmp/spans.jsonl").unwrap();
+/// reporter.set_max_file_size(16 * 1024 * 1024);
+/// reporter.report(&spans).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FileReporter {
+    path: PathBuf,
+    process: jaeger::Process,
+    max_file_size: Option<u64>,
+    rotation_interval: Option<Duration>,
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+    file: Mutex<ActiveFile>,
+    counters: ReporterCounters,
+}
+impl FileReporter {
+    /// Makes a new `FileReporter` instance which appends spans to the file `path`.
+    ///
+    /// The file is created if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to open the file,
+    /// this function will return an error which has the kind `ErrorKind::Other`.
+    pub fn new<P: AsRef<Path>>(service_name: &str, path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = track!(ActiveFile::open(&path))?;
+        Ok(FileReporter {
+            path,
+            process: super::default_process(service_name),
+            max_file_size: None,
+            rotation_interval: None,
+            #[cfg(feature = "gzip")]
+            gzip: false,
+            file: Mutex::new(file),
+            counters: ReporterCounters::default(),
+        })
+    }
+
+    /// Returns the path of the file to which spans are written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the snapshot of the statistics of this reporter.
+    pub fn stats(&self) -> ReporterStats {
+        self.counters.snapshot()
+    }
+
+    /// Sets the size in bytes at which the file is rotated.
+    ///
+    /// By default, the file is not rotated by size.
+    pub fn set_max_file_size(&mut self, size: u64) {
+        self.max_file_size = Some(size);
+    }
+
+    /// Sets the interval at which the file is rotated.
+    ///
+    /// The interval is measured from when the file is opened, and the rotation happens on the
+    /// first report after the interval elapses (empty files are not rotated).
+    ///
+    /// By default, the file is not rotated by time.
+    pub fn set_rotation_interval(&mut self, interval: Duration) {
+        self.rotation_interval = Some(interval);
+    }
+
+    /// Sets whether rotated files are compressed by gzip.
+    ///
+    /// The default value is `false`.
+    #[cfg(feature = "gzip")]
+    pub fn set_gzip(&mut self, enabled: bool) {
+        self.gzip = enabled;
+    }
+
+    /// Adds `tag` to this service.
+    pub fn add_service_tag(&mut self, tag: Tag) {
+        self.process.tags.push((&tag).into());
+    }
+
+    /// Reports `spans`.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to write the spans or to rotate the file,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        let process = json::jaeger_process(self.process.clone());
+        let mut file = self.file();
+        let mut bytes = 0;
+        for span in spans {
+            let mut line = json::jaeger_span(jaeger::Span::from(span))
+                .with("process", process.clone())
+                .to_string();
+            line.push('\n');
+            if let Err(e) = self.write_line(&mut file, line.as_bytes()) {
+                self.counters.record_send_failure();
+                return Err(track!(e));
+            }
+            bytes += line.len();
+        }
+        if let Err(e) = file.writer.flush() {
+            self.counters.record_send_failure();
+            return Err(track!(error::from_io_error(e)));
+        }
+        self.counters.record_sent(bytes, spans.len());
+        Ok(())
+    }
+
+    fn write_line(&self, file: &mut ActiveFile, line: &[u8]) -> Result<()> {
+        let exceeds_size = self
+            .max_file_size
+            .is_some_and(|max| file.size + line.len() as u64 > max);
+        let expired = self
+            .rotation_interval
+            .is_some_and(|interval| file.opened_at.elapsed() >= interval);
+        if file.size > 0 && (exceeds_size || expired) {
+            track!(self.rotate(file))?;
+        }
+        track!(file.writer.write_all(line).map_err(error::from_io_error))?;
+        file.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&self, file: &mut ActiveFile) -> Result<()> {
+        track!(file.writer.flush().map_err(error::from_io_error))?;
+        let rotated = self.rotated_path();
+        track!(fs::rename(&self.path, &rotated).map_err(error::from_io_error))?;
+        *file = track!(ActiveFile::open(&self.path))?;
+
+        #[cfg(feature = "gzip")]
+        if self.gzip {
+            track!(compress(&rotated))?;
+        }
+        Ok(())
+    }
+
+    fn rotated_path(&self) -> PathBuf {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let mut suffix = millis.to_string();
+        let mut i = 0;
+        loop {
+            let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+            name.push(format!(".{}", suffix));
+            let path = self.path.with_file_name(&name);
+            name.push(".gz");
+            if !path.exists() && !self.path.with_file_name(&name).exists() {
+                return path;
+            }
+            i += 1;
+            suffix = format!("{}-{}", millis, i);
+        }
+    }
+
+    fn file(&self) -> MutexGuard<'_, ActiveFile> {
+        self.file.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+impl Reporter for FileReporter {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(FileReporter::report(self, spans))
+    }
+}
+
+#[derive(Debug)]
+struct ActiveFile {
+    writer: BufWriter<File>,
+    size: u64,
+    opened_at: Instant,
+}
+impl ActiveFile {
+    fn open(path: &Path) -> Result<Self> {
+        let file = track!(OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(error::from_io_error))?;
+        let size = track!(file.metadata().map_err(error::from_io_error))?.len();
+        Ok(ActiveFile {
+            writer: BufWriter::new(file),
+            size,
+            opened_at: Instant::now(),
+        })
+    }
+}
+
+/// Compresses the file `path` to `{path}.gz` and removes the original.
+#[cfg(feature = "gzip")]
+fn compress(path: &Path) -> Result<()> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let mut gz_path = path.as_os_str().to_os_string();
+    gz_path.push(".gz");
+    let mut input = track!(File::open(path).map_err(error::from_io_error))?;
+    let output = track!(File::create(&gz_path).map_err(error::from_io_error))?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    track!(std::io::copy(&mut input, &mut encoder).map_err(error::from_io_error))?;
+    track!(encoder.finish().map_err(error::from_io_error))?;
+    track!(fs::remove_file(path).map_err(error::from_io_error))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reporter::temp_dir;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use trackable::result::TestResult;
+
+    fn rotated_files(dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in track!(fs::read_dir(dir).map_err(error::from_io_error))? {
+            let path = track!(entry.map_err(error::from_io_error))?.path();
+            if path.file_name() != Some("spans.jsonl".as_ref()) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    #[test]
+    fn file_reporter_works() -> TestResult {
+        let dir = temp_dir("file_reporter_works");
+        track_any_err!(fs::create_dir_all(&dir))?;
+        let path = dir.join("spans.jsonl");
+        let mut reporter = track!(FileReporter::new("file_test", &path))?;
+        reporter.add_service_tag(Tag::new("foo", "bar"));
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        tracer.span("bar").start();
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let content = track_any_err!(fs::read_to_string(&path))?;
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(r#"{"traceID":""#));
+        assert!(lines[0].contains(r#","operationName":"foo","#));
+        assert!(lines[1].contains(r#","operationName":"bar","#));
+        assert!(lines[1].contains(r#","process":{"serviceName":"file_test","tags":["#));
+        assert!(lines[1].ends_with(r#"{"key":"foo","type":"string","value":"bar"}]}}"#));
+        assert_eq!(reporter.stats().spans_sent, 2);
+        assert_eq!(reporter.stats().bytes_written, content.len() as u64);
+
+        track_any_err!(fs::remove_dir_all(&dir))?;
+        Ok(())
+    }
+
+    #[test]
+    fn size_based_rotation_works() -> TestResult {
+        let dir = temp_dir("size_based_rotation_works");
+        track_any_err!(fs::create_dir_all(&dir))?;
+        let path = dir.join("spans.jsonl");
+        let mut reporter = track!(FileReporter::new("file_test", &path))?;
+        reporter.set_max_file_size(1);
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        for _ in 0..3 {
+            tracer.span("foo").start();
+            track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+        }
+
+        let rotated = track!(rotated_files(&dir))?;
+        assert_eq!(rotated.len(), 2);
+        for path in rotated.iter().chain(Some(&path)) {
+            let content = track_any_err!(fs::read_to_string(path))?;
+            assert_eq!(content.lines().count(), 1);
+        }
+
+        track_any_err!(fs::remove_dir_all(&dir))?;
+        Ok(())
+    }
+
+    #[test]
+    fn time_based_rotation_works() -> TestResult {
+        let dir = temp_dir("time_based_rotation_works");
+        track_any_err!(fs::create_dir_all(&dir))?;
+        let path = dir.join("spans.jsonl");
+        let mut reporter = track!(FileReporter::new("file_test", &path))?;
+        reporter.set_rotation_interval(Duration::from_millis(0));
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        tracer.span("bar").start();
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let rotated = track!(rotated_files(&dir))?;
+        assert_eq!(rotated.len(), 1);
+        let content = track_any_err!(fs::read_to_string(&rotated[0]))?;
+        assert!(content.contains(r#""operationName":"foo""#));
+        let content = track_any_err!(fs::read_to_string(&path))?;
+        assert!(content.contains(r#""operationName":"bar""#));
+
+        track_any_err!(fs::remove_dir_all(&dir))?;
+        Ok(())
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_works() -> TestResult {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let dir = temp_dir("gzip_works");
+        track_any_err!(fs::create_dir_all(&dir))?;
+        let path = dir.join("spans.jsonl");
+        let mut reporter = track!(FileReporter::new("file_test", &path))?;
+        reporter.set_max_file_size(1);
+        reporter.set_gzip(true);
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        tracer.span("bar").start();
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let rotated = track!(rotated_files(&dir))?;
+        assert_eq!(rotated.len(), 1);
+        assert_eq!(rotated[0].extension(), Some("gz".as_ref()));
+        let mut content = String::new();
+        let file = track_any_err!(File::open(&rotated[0]))?;
+        track_any_err!(GzDecoder::new(file).read_to_string(&mut content))?;
+        assert!(content.contains(r#""operationName":"foo""#));
+
+        track_any_err!(fs::remove_dir_all(&dir))?;
+        Ok(())
+    }
+}
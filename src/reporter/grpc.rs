@@ -0,0 +1,296 @@
+use super::stats::{ReporterCounters, ReporterStats};
+use crate::error;
+use crate::proto::jaeger as proto;
+use crate::span::FinishedSpan;
+use crate::thrift::jaeger;
+use crate::{Error, ErrorKind, Result};
+use prost::Message;
+use rustracing::tag::Tag;
+use std::time::Duration;
+use tonic::client::Grpc;
+use tonic::codec::ProstCodec;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::{Channel, Endpoint};
+use tonic::GrpcMethod;
+use trackable::error::ErrorKindExt;
+
+const SERVICE_NAME: &str = "jaeger.api_v2.CollectorService";
+const POST_SPANS_METHOD: &str = "PostSpans";
+const POST_SPANS_PATH: &str = "/jaeger.api_v2.CollectorService/PostSpans";
+
+/// Reporter which sends spans to the collector over gRPC
+/// (i.e., the `jaeger.api_v2.CollectorService/PostSpans` method).
+///
+/// This is useful in environments where no agent is available.
+/// Unlike the other reporters, this reporter is asynchronous and must be used within a tokio runtime.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::JaegerGrpcReporter;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// let spans = span_rx.try_iter().collect::<Vec<_>>();
+///
+/// let reporter = JaegerGrpcReporter::connect("sample_service", "http://jaeger-collector:14250")
+///     .await
+///     .unwrap();
+/// reporter.report(&spans).await.unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JaegerGrpcReporter {
+    client: Grpc<Channel>,
+    process: jaeger::Process,
+    counters: ReporterCounters,
+}
+impl JaegerGrpcReporter {
+    /// Connects to the collector at `endpoint` (e.g., `"http://127.0.0.1:14250"`)
+    /// and makes a new `JaegerGrpcReporter` instance.
+    ///
+    /// The timeout of each of connecting and calling `PostSpans` is 5 seconds.
+    ///
+    /// # Errors
+    ///
+    /// If `endpoint` is not a valid URI,
+    /// this function will return an error which has the kind `ErrorKind::InvalidInput`.
+    ///
+    /// If it fails to connect to the collector,
+    /// this function will return an error which has the kind `ErrorKind::Other`.
+    pub async fn connect(service_name: &str, endpoint: &str) -> Result<Self> {
+        let timeout = Duration::from_secs(5);
+        let endpoint = track!(Endpoint::from_shared(endpoint.to_owned())
+            .map_err(|e| Error::from(ErrorKind::InvalidInput.cause(e))))?
+        .connect_timeout(timeout)
+        .timeout(timeout);
+        let channel = track!(endpoint
+            .connect()
+            .await
+            .map_err(error::from_grpc_transport_error))?;
+        Ok(JaegerGrpcReporter {
+            client: Grpc::new(channel),
+            process: super::default_process(service_name),
+            counters: ReporterCounters::default(),
+        })
+    }
+
+    /// Adds `tag` to this service.
+    pub fn add_service_tag(&mut self, tag: Tag) {
+        self.process.tags.push((&tag).into());
+    }
+
+    /// Returns the snapshot of the statistics of this reporter.
+    pub fn stats(&self) -> ReporterStats {
+        self.counters.snapshot()
+    }
+
+    /// Reports `spans`.
+    ///
+    /// All of `spans` are sent by a single `PostSpans` call.
+    ///
+    /// # Errors
+    ///
+    /// If the collector rejects the request as an invalid argument,
+    /// this method will return an error which has the kind `ErrorKind::InvalidInput`.
+    ///
+    /// If it fails to send the request or the collector responds with the other error status,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub async fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        let batch = proto::Batch {
+            spans: spans
+                .iter()
+                .map(|span| proto::Span::from(jaeger::Span::from(span)))
+                .collect(),
+            process: Some(self.process.clone().into()),
+        };
+        let request = proto::PostSpansRequest { batch: Some(batch) };
+        let bytes = request.encoded_len();
+        match self.post_spans(request).await {
+            Ok(()) => {
+                self.counters.record_sent(bytes, spans.len());
+                Ok(())
+            }
+            Err(e) => {
+                self.counters.record_send_failure();
+                Err(track!(e))
+            }
+        }
+    }
+
+    async fn post_spans(&self, request: proto::PostSpansRequest) -> Result<()> {
+        let mut client = self.client.clone();
+        track!(client
+            .ready()
+            .await
+            .map_err(error::from_grpc_transport_error))?;
+
+        let mut request = tonic::Request::new(request);
+        request
+            .extensions_mut()
+            .insert(GrpcMethod::new(SERVICE_NAME, POST_SPANS_METHOD));
+        let codec: ProstCodec<proto::PostSpansRequest, proto::PostSpansResponse> =
+            ProstCodec::default();
+        track!(client
+            .unary(request, PathAndQuery::from_static(POST_SPANS_PATH), codec)
+            .await
+            .map_err(error::from_grpc_status))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tonic::body::BoxBody;
+    use tonic::codegen::{empty_body, http, Body, BoxFuture, Service, StdError};
+    use tonic::server::{NamedService, UnaryService};
+    use tonic::transport::server::TcpIncoming;
+    use tonic::transport::Server;
+    use tonic::{Code, Status};
+    use trackable::result::TestResult;
+
+    #[tokio::test]
+    async fn grpc_reporter_works() -> TestResult {
+        let (endpoint, mut rx) = track!(start_collector(Code::Ok).await)?;
+        let mut reporter = track!(JaegerGrpcReporter::connect("grpc_test", &endpoint).await)?;
+        reporter.add_service_tag(Tag::new("foo", "bar"));
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        tracer.span("bar").start();
+        track!(
+            reporter
+                .report(&span_rx.try_iter().collect::<Vec<_>>())
+                .await
+        )?;
+
+        let request = track_assert_some!(rx.recv().await, ErrorKind::Other);
+        let batch = track_assert_some!(request.batch, ErrorKind::Other);
+        let process = track_assert_some!(batch.process, ErrorKind::Other);
+        assert_eq!(process.service_name, "grpc_test");
+        assert!(process
+            .tags
+            .iter()
+            .any(|t| t.key == "foo" && t.v_str == "bar"));
+        assert_eq!(batch.spans.len(), 2);
+        assert_eq!(batch.spans[0].operation_name, "foo");
+        assert_eq!(batch.spans[0].trace_id.len(), 16);
+        assert_eq!(batch.spans[0].span_id.len(), 8);
+        assert_eq!(reporter.stats().spans_sent, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn error_status_is_reported() -> TestResult {
+        let (endpoint, _rx) = track!(start_collector(Code::InvalidArgument).await)?;
+        let reporter = track!(JaegerGrpcReporter::connect("grpc_test", &endpoint).await)?;
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        let e = track_assert_some!(
+            reporter
+                .report(&span_rx.try_iter().collect::<Vec<_>>())
+                .await
+                .err(),
+            ErrorKind::Other
+        );
+        assert_eq!(*e.kind(), ErrorKind::InvalidInput);
+        assert_eq!(reporter.stats().send_failures, 1);
+        Ok(())
+    }
+
+    /// Starts a collector stand-in which responds to each request with `code`.
+    async fn start_collector(
+        code: Code,
+    ) -> Result<(String, mpsc::UnboundedReceiver<proto::PostSpansRequest>)> {
+        let listener = track!(TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(error::from_io_error))?;
+        let addr = track!(listener.local_addr().map_err(error::from_io_error))?;
+        let incoming = track!(TcpIncoming::from_listener(listener, true, None)
+            .map_err(|e| Error::from(ErrorKind::Other.cause(e))))?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let collector = CollectorServer(Arc::new(Collector { code, tx }));
+        tokio::spawn(
+            Server::builder()
+                .add_service(collector)
+                .serve_with_incoming(incoming),
+        );
+        Ok((format!("http://{}", addr), rx))
+    }
+
+    #[derive(Debug)]
+    struct Collector {
+        code: Code,
+        tx: mpsc::UnboundedSender<proto::PostSpansRequest>,
+    }
+    struct PostSpansSvc(Arc<Collector>);
+    impl UnaryService<proto::PostSpansRequest> for PostSpansSvc {
+        type Response = proto::PostSpansResponse;
+        type Future = BoxFuture<tonic::Response<Self::Response>, Status>;
+
+        fn call(&mut self, request: tonic::Request<proto::PostSpansRequest>) -> Self::Future {
+            let _ = self.0.tx.send(request.into_inner());
+            let code = self.0.code;
+            Box::pin(async move {
+                if code == Code::Ok {
+                    Ok(tonic::Response::new(proto::PostSpansResponse {}))
+                } else {
+                    Err(Status::new(code, "rejected"))
+                }
+            })
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct CollectorServer(Arc<Collector>);
+    impl NamedService for CollectorServer {
+        const NAME: &'static str = SERVICE_NAME;
+    }
+    impl<B> Service<http::Request<B>> for CollectorServer
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        type Response = http::Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::result::Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: http::Request<B>) -> Self::Future {
+            let collector = Arc::clone(&self.0);
+            Box::pin(async move {
+                if req.uri().path() != POST_SPANS_PATH {
+                    let mut response = http::Response::new(empty_body());
+                    response
+                        .headers_mut()
+                        .insert(Status::GRPC_STATUS, (Code::Unimplemented as i32).into());
+                    return Ok(response);
+                }
+                let codec: ProstCodec<proto::PostSpansResponse, proto::PostSpansRequest> =
+                    ProstCodec::default();
+                let mut grpc = tonic::server::Grpc::new(codec);
+                Ok(grpc.unary(PostSpansSvc(collector), req).await)
+            })
+        }
+    }
+}
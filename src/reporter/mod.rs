@@ -0,0 +1,1075 @@
+//! Reporter to the [jaeger agent]
+//!
+//! [jaeger agent]: http://jaeger.readthedocs.io/en/latest/deployment/#agent
+use self::retry::{EncodedPacket, RetryBuffer};
+use self::stats::ReporterCounters;
+use crate::constants;
+use crate::error;
+use crate::span::FinishedSpan;
+use crate::thrift::{agent, jaeger};
+use crate::{Error, ErrorKind, Result};
+use rustracing::tag::Tag;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use thrift_codec::data::Struct;
+use thrift_codec::message::Message;
+use thrift_codec::{BinaryEncode, CompactEncode};
+
+pub use self::collector::{HttpTransport, JaegerHttpReporter};
+pub use self::console::ConsoleReporter;
+pub use self::fanout::FanOutReporter;
+pub use self::file::FileReporter;
+#[cfg(feature = "grpc")]
+pub use self::grpc::JaegerGrpcReporter;
+#[cfg(feature = "otlp")]
+pub use self::otlp::{OtlpEncoding, OtlpHttpReporter};
+pub use self::remote::{PriorityPolicy, RemoteReporter, RemoteReporterBuilder};
+pub use self::retry::RetryPolicy;
+pub use self::routing::{Route, RoutingReporter};
+pub use self::spool::Spool;
+pub use self::stats::ReporterStats;
+#[cfg(feature = "tokio")]
+pub use self::tokio_reporter::{AsyncReporter, AsyncReporterBuilder};
+#[cfg(unix)]
+pub use self::transport::UnixDatagramTransport;
+pub use self::transport::{Transport, UdpTransport};
+pub use self::zipkin::ZipkinCompactReporter;
+pub use self::zipkin_v2::ZipkinV2Reporter;
+
+mod collector;
+mod console;
+mod fanout;
+mod file;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "otlp")]
+mod otlp;
+mod remote;
+mod retry;
+mod routing;
+mod spool;
+mod stats;
+#[cfg(feature = "tokio")]
+mod tokio_reporter;
+mod transport;
+mod zipkin;
+mod zipkin_v2;
+
+/// Reporter for the agent which accepts jaeger.thrift over compact thrift protocol.
+///
+/// The packets are sent via UDP by default, and other transports can be used through `with_transport`.
+#[derive(Debug)]
+pub struct JaegerCompactReporter<T = UdpTransport>(JaegerReporter<T>);
+impl JaegerCompactReporter {
+    /// Makes a new `JaegerCompactReporter` instance.
+    ///
+    /// # Errors
+    ///
+    /// If the UDP socket used to report spans can not be bound to `0.0.0.0:0`,
+    /// it will return an error which has the kind `ErrorKind::Other`.
+    pub fn new(service_name: &str) -> Result<Self> {
+        let agent = SocketAddr::from(([127, 0, 0, 1], Protocol::Compact.default_port()));
+        let transport = track!(UdpTransport::new(agent))?;
+        Ok(JaegerCompactReporter(JaegerReporter::new(
+            service_name,
+            BatchFormat::Agent(Protocol::Compact),
+            transport,
+        )))
+    }
+
+    /// Sets the address of the report destination agent to `addr`.
+    ///
+    /// The default address is `127.0.0.1:6831`.
+    ///
+    /// Note that you may also need to call `set_reporter_addr` if the `addr` is IPv6 or non localhost address.
+    pub fn set_agent_addr(&mut self, addr: SocketAddr) {
+        self.0.transport.set_agent_addr(addr);
+    }
+
+    /// Sets the address of the report destination agent to `host` (e.g., `"jaeger-agent:6831"`).
+    ///
+    /// `host` is resolved immediately and then re-resolved periodically (see `set_resolve_interval`)
+    /// so that the change of the agent address is followed.
+    /// If the address family of the agent changes, the reporter socket is rebound to
+    /// an address of the new family.
+    ///
+    /// # Errors
+    ///
+    /// If `host` can not be resolved, this method will return an error.
+    pub fn set_agent_host(&mut self, host: &str) -> Result<()> {
+        track!(self.0.transport.set_agent_host(host))
+    }
+
+    /// Sets the interval at which the agent host name given by `set_agent_host` is re-resolved.
+    ///
+    /// The default value is `Duration::from_secs(30)`.
+    pub fn set_resolve_interval(&mut self, interval: Duration) {
+        self.0.transport.set_resolve_interval(interval);
+    }
+
+    /// Sets the address to which the reporter bind.
+    ///
+    /// The default address is `127.0.0.1:0`.
+    pub fn set_reporter_addr(&mut self, addr: SocketAddr) -> Result<()> {
+        track!(self.0.transport.set_reporter_addr(addr))
+    }
+
+    /// Enables or disables the connected mode.
+    ///
+    /// In the connected mode, the UDP socket is connected to the agent address.
+    /// It makes it possible to detect that the agent is unreachable
+    /// through ICMP port-unreachable errors (see `is_agent_healthy` and `is_agent_unreachable`).
+    ///
+    /// The connected mode is disabled by default.
+    pub fn set_connected_mode(&mut self, enabled: bool) -> Result<()> {
+        track!(self.0.transport.set_connected_mode(enabled))
+    }
+}
+impl<T: Transport> JaegerCompactReporter<T> {
+    /// Makes a new `JaegerCompactReporter` instance which sends packets via `transport`.
+    pub fn with_transport(service_name: &str, transport: T) -> Self {
+        JaegerCompactReporter(JaegerReporter::new(
+            service_name,
+            BatchFormat::Agent(Protocol::Compact),
+            transport,
+        ))
+    }
+
+    /// Returns a reference to the transport of this reporter.
+    pub fn transport(&self) -> &T {
+        &self.0.transport
+    }
+
+    /// Returns a mutable reference to the transport of this reporter.
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.0.transport
+    }
+
+    /// Returns `false` if it is detected that the agent is unreachable.
+    ///
+    /// For UDP, the detection works only in the connected mode (see `set_connected_mode`),
+    /// and the status is updated when spans are reported or this method is called.
+    pub fn is_agent_healthy(&self) -> bool {
+        self.0.transport.is_agent_healthy()
+    }
+
+    /// Returns the snapshot of the statistics of this reporter.
+    pub fn stats(&self) -> ReporterStats {
+        self.0.counters.snapshot()
+    }
+
+    /// Sets the maximum size of a packet sent to the agent.
+    ///
+    /// Spans reported at once are split into multiple packets if their encoded size exceeds this.
+    ///
+    /// The default value is `65000`.
+    pub fn set_max_packet_size(&mut self, size: usize) {
+        self.0.set_max_packet_size(size);
+    }
+
+    /// Sets the policy for retrying failed sends.
+    ///
+    /// By default, failed sends are not retried.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.0.set_retry_policy(policy);
+    }
+
+    /// Sets the maximum number of packets kept in the retry buffer.
+    ///
+    /// Packets that could not be sent (even after retries) are kept in the buffer,
+    /// and re-sent before newly reported spans once the transport recovers.
+    /// If the buffer is full, the oldest packet is dropped
+    /// (counted in `ReporterStats::retry_buffer_overflows`).
+    ///
+    /// The default value is `0` (i.e., the retry buffer is disabled).
+    pub fn set_retry_buffer_size(&mut self, size: usize) {
+        self.0.set_retry_buffer_size(size);
+    }
+
+    /// Sets the spool where packets that could not be sent are stored.
+    ///
+    /// If a spool is set, it is used instead of the in-memory retry buffer
+    /// (see `set_retry_buffer_size`), and the spooled packets are replayed in order
+    /// once the transport recovers.
+    /// This includes the packets left in the spool by a previous process.
+    pub fn set_spool(&mut self, spool: Spool) {
+        self.0.set_spool(spool);
+    }
+
+    /// Adds `tag` to this service.
+    pub fn add_service_tag(&mut self, tag: Tag) {
+        self.0.add_service_tag(tag);
+    }
+
+    /// Reports `spans`.
+    ///
+    /// The spans are split into multiple packets if they do not fit in a packet.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to encode `spans` to the thrift compact format (i.e., a bug of this crate),
+    /// this method will return an error which has the kind `ErrorKind::InvalidInput`.
+    ///
+    /// If some of `spans` are too large to fit in a packet even alone,
+    /// they are dropped and this method will return an error which has the kind `ErrorKind::InvalidInput`
+    /// after sending the remaining spans.
+    ///
+    /// If it fails to send the encoded binary to the jaeger agent,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    /// In that case, the unsent packets are kept in the retry buffer or the spool if they are enabled.
+    /// If the failure is caused by the agent being unreachable,
+    /// `is_agent_unreachable` returns `true` for the error.
+    pub fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(self.0.report(spans))
+    }
+}
+impl<T: Transport> Reporter for JaegerCompactReporter<T> {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(self.0.report(spans))
+    }
+}
+
+/// Reporter for the agent which accepts jaeger.thrift over binary thrift protocol.
+///
+/// The packets are sent via UDP by default, and other transports can be used through `with_transport`.
+#[derive(Debug)]
+pub struct JaegerBinaryReporter<T = UdpTransport>(JaegerReporter<T>);
+impl JaegerBinaryReporter {
+    /// Makes a new `JaegerBinaryReporter` instance.
+    ///
+    /// # Errors
+    ///
+    /// If the UDP socket used to report spans can not be bound to `0.0.0.0:0`,
+    /// it will return an error which has the kind `ErrorKind::Other`.
+    pub fn new(service_name: &str) -> Result<Self> {
+        let agent = SocketAddr::from(([127, 0, 0, 1], Protocol::Binary.default_port()));
+        let transport = track!(UdpTransport::new(agent))?;
+        Ok(JaegerBinaryReporter(JaegerReporter::new(
+            service_name,
+            BatchFormat::Agent(Protocol::Binary),
+            transport,
+        )))
+    }
+
+    /// Sets the address of the report destination agent to `addr`.
+    ///
+    /// The default address is `127.0.0.1:6832`.
+    ///
+    /// Note that you may also need to call `set_reporter_addr` if the `addr` is IPv6 or non localhost address.
+    pub fn set_agent_addr(&mut self, addr: SocketAddr) {
+        self.0.transport.set_agent_addr(addr);
+    }
+
+    /// Sets the address of the report destination agent to `host` (e.g., `"jaeger-agent:6832"`).
+    ///
+    /// `host` is resolved immediately and then re-resolved periodically (see `set_resolve_interval`)
+    /// so that the change of the agent address is followed.
+    /// If the address family of the agent changes, the reporter socket is rebound to
+    /// an address of the new family.
+    ///
+    /// # Errors
+    ///
+    /// If `host` can not be resolved, this method will return an error.
+    pub fn set_agent_host(&mut self, host: &str) -> Result<()> {
+        track!(self.0.transport.set_agent_host(host))
+    }
+
+    /// Sets the interval at which the agent host name given by `set_agent_host` is re-resolved.
+    ///
+    /// The default value is `Duration::from_secs(30)`.
+    pub fn set_resolve_interval(&mut self, interval: Duration) {
+        self.0.transport.set_resolve_interval(interval);
+    }
+
+    /// Sets the address to which the report bind.
+    ///
+    /// The default address is `127.0.0.1:0`.
+    pub fn set_reporter_addr(&mut self, addr: SocketAddr) -> Result<()> {
+        track!(self.0.transport.set_reporter_addr(addr))
+    }
+
+    /// Enables or disables the connected mode.
+    ///
+    /// In the connected mode, the UDP socket is connected to the agent address.
+    /// It makes it possible to detect that the agent is unreachable
+    /// through ICMP port-unreachable errors (see `is_agent_healthy` and `is_agent_unreachable`).
+    ///
+    /// The connected mode is disabled by default.
+    pub fn set_connected_mode(&mut self, enabled: bool) -> Result<()> {
+        track!(self.0.transport.set_connected_mode(enabled))
+    }
+}
+impl<T: Transport> JaegerBinaryReporter<T> {
+    /// Makes a new `JaegerBinaryReporter` instance which sends packets via `transport`.
+    pub fn with_transport(service_name: &str, transport: T) -> Self {
+        JaegerBinaryReporter(JaegerReporter::new(
+            service_name,
+            BatchFormat::Agent(Protocol::Binary),
+            transport,
+        ))
+    }
+
+    /// Returns a reference to the transport of this reporter.
+    pub fn transport(&self) -> &T {
+        &self.0.transport
+    }
+
+    /// Returns a mutable reference to the transport of this reporter.
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.0.transport
+    }
+
+    /// Returns `false` if it is detected that the agent is unreachable.
+    ///
+    /// For UDP, the detection works only in the connected mode (see `set_connected_mode`),
+    /// and the status is updated when spans are reported or this method is called.
+    pub fn is_agent_healthy(&self) -> bool {
+        self.0.transport.is_agent_healthy()
+    }
+
+    /// Returns the snapshot of the statistics of this reporter.
+    pub fn stats(&self) -> ReporterStats {
+        self.0.counters.snapshot()
+    }
+
+    /// Sets the maximum size of a packet sent to the agent.
+    ///
+    /// Spans reported at once are split into multiple packets if their encoded size exceeds this.
+    ///
+    /// The default value is `65000`.
+    pub fn set_max_packet_size(&mut self, size: usize) {
+        self.0.set_max_packet_size(size);
+    }
+
+    /// Sets the policy for retrying failed sends.
+    ///
+    /// By default, failed sends are not retried.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.0.set_retry_policy(policy);
+    }
+
+    /// Sets the maximum number of packets kept in the retry buffer.
+    ///
+    /// Packets that could not be sent (even after retries) are kept in the buffer,
+    /// and re-sent before newly reported spans once the transport recovers.
+    /// If the buffer is full, the oldest packet is dropped
+    /// (counted in `ReporterStats::retry_buffer_overflows`).
+    ///
+    /// The default value is `0` (i.e., the retry buffer is disabled).
+    pub fn set_retry_buffer_size(&mut self, size: usize) {
+        self.0.set_retry_buffer_size(size);
+    }
+
+    /// Sets the spool where packets that could not be sent are stored.
+    ///
+    /// If a spool is set, it is used instead of the in-memory retry buffer
+    /// (see `set_retry_buffer_size`), and the spooled packets are replayed in order
+    /// once the transport recovers.
+    /// This includes the packets left in the spool by a previous process.
+    pub fn set_spool(&mut self, spool: Spool) {
+        self.0.set_spool(spool);
+    }
+
+    /// Adds `tag` to this service.
+    pub fn add_service_tag(&mut self, tag: Tag) {
+        self.0.add_service_tag(tag);
+    }
+
+    /// Reports `spans`.
+    ///
+    /// The spans are split into multiple packets if they do not fit in a packet.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to encode `spans` to the thrift binary format (i.e., a bug of this crate),
+    /// this method will return an error which has the kind `ErrorKind::InvalidInput`.
+    ///
+    /// If some of `spans` are too large to fit in a packet even alone,
+    /// they are dropped and this method will return an error which has the kind `ErrorKind::InvalidInput`
+    /// after sending the remaining spans.
+    ///
+    /// If it fails to send the encoded binary to the jaeger agent,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    /// In that case, the unsent packets are kept in the retry buffer or the spool if they are enabled.
+    /// If the failure is caused by the agent being unreachable,
+    /// `is_agent_unreachable` returns `true` for the error.
+    pub fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(self.0.report(spans))
+    }
+}
+impl<T: Transport> Reporter for JaegerBinaryReporter<T> {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(self.0.report(spans))
+    }
+}
+
+/// Common interface of the reporters which report finished spans synchronously.
+///
+/// This makes it possible to write code generic over reporters and
+/// to combine reporters (e.g., by `FanOutReporter`).
+pub trait Reporter {
+    /// Reports `spans`.
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()>;
+}
+impl<R: Reporter + ?Sized> Reporter for Box<R> {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!((**self).report(spans))
+    }
+}
+impl<R: Reporter + ?Sized> Reporter for Arc<R> {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!((**self).report(spans))
+    }
+}
+
+/// Returns `true` if `error` indicates that the agent is unreachable.
+///
+/// Note that UDP reporters report such errors only in the connected mode
+/// (e.g., `JaegerCompactReporter::set_connected_mode`).
+pub fn is_agent_unreachable(error: &Error) -> bool {
+    error.concrete_cause::<io::Error>().is_some_and(|e| {
+        matches!(
+            e.kind(),
+            io::ErrorKind::ConnectionRefused | io::ErrorKind::NotFound
+        )
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Protocol {
+    Compact,
+    Binary,
+}
+impl Protocol {
+    fn default_port(self) -> u16 {
+        match self {
+            Protocol::Compact => 6831,
+            Protocol::Binary => 6832,
+        }
+    }
+
+    fn encode<T>(self, data: &T) -> Result<Vec<u8>>
+    where
+        T: CompactEncode + BinaryEncode,
+    {
+        let mut bytes = Vec::new();
+        match self {
+            Protocol::Compact => track!(data
+                .compact_encode(&mut bytes)
+                .map_err(error::from_thrift_error))?,
+            Protocol::Binary => track!(data
+                .binary_encode(&mut bytes)
+                .map_err(error::from_thrift_error))?,
+        }
+        Ok(bytes)
+    }
+}
+
+/// Format of the batches sent by `JaegerReporter`.
+#[derive(Debug, Clone, Copy)]
+enum BatchFormat {
+    /// `emitBatch` messages accepted by the agent.
+    Agent(Protocol),
+
+    /// Bare `Batch` structs in the binary thrift protocol accepted by the collector.
+    Collector,
+}
+impl BatchFormat {
+    fn encode_batch(self, batch: jaeger::Batch) -> Result<Vec<u8>> {
+        match self {
+            BatchFormat::Agent(protocol) => {
+                let message = Message::from(agent::EmitBatchNotification { batch });
+                track!(protocol.encode(&message))
+            }
+            BatchFormat::Collector => track!(Protocol::Binary.encode(&Struct::from(batch))),
+        }
+    }
+
+    fn encode_span(self, span: jaeger::Span) -> Result<Vec<u8>> {
+        let protocol = match self {
+            BatchFormat::Agent(protocol) => protocol,
+            BatchFormat::Collector => Protocol::Binary,
+        };
+        track!(protocol.encode(&Struct::from(span)))
+    }
+}
+
+/// The default maximum size of a packet sent to the agent.
+///
+/// This is slightly smaller than the maximum UDP payload size (65507 bytes) to leave room
+/// for the difference between the estimated and the actual sizes.
+const DEFAULT_MAX_PACKET_SIZE: usize = 65000;
+
+/// The maximum number of bytes the header of a list can grow when elements are added to it.
+const LIST_HEADER_SLACK: usize = 5;
+
+/// The interval between attempts to re-send the buffered packets during a shutdown.
+const RESEND_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Groups `spans` so that each group can be sent as a packet within `max_packet_size` bytes.
+///
+/// `overhead` is the encoded size of a packet which has no spans,
+/// and `encoded_size` returns the encoded size of a span.
+///
+/// Spans that cannot fit in a packet even alone are dropped,
+/// and the number of them is returned together.
+fn split_into_packets<S, F>(
+    spans: Vec<S>,
+    overhead: usize,
+    max_packet_size: usize,
+    mut encoded_size: F,
+) -> Result<(Vec<Vec<S>>, usize)>
+where
+    F: FnMut(&S) -> Result<usize>,
+{
+    let overhead = overhead + LIST_HEADER_SLACK;
+    let mut packets = Vec::new();
+    let mut packet = Vec::new();
+    let mut packet_size = overhead;
+    let mut dropped = 0;
+    for span in spans {
+        let span_size = track!(encoded_size(&span))?;
+        if overhead + span_size > max_packet_size {
+            dropped += 1;
+            continue;
+        }
+        if packet_size + span_size > max_packet_size {
+            packets.push(std::mem::take(&mut packet));
+            packet_size = overhead;
+        }
+        packet.push(span);
+        packet_size += span_size;
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+    Ok((packets, dropped))
+}
+
+/// Returns an error which has the kind `ErrorKind::InvalidInput` if `dropped` is not zero
+/// (i.e., some spans were dropped by `split_into_packets`).
+fn check_too_large_spans(dropped: usize, max_packet_size: usize) -> Result<()> {
+    if dropped > 0 {
+        track_panic!(
+            ErrorKind::InvalidInput,
+            "{} spans were dropped because they exceed the maximum packet size ({} bytes)",
+            dropped,
+            max_packet_size
+        );
+    }
+    Ok(())
+}
+
+/// Makes the process of `service_name` which has the client version, the hostname and the IP address tags.
+pub(crate) fn default_process(service_name: &str) -> jaeger::Process {
+    let mut tags = vec![Tag::new(
+        constants::JAEGER_CLIENT_VERSION_TAG_KEY,
+        constants::JAEGER_CLIENT_VERSION,
+    )];
+    if let Ok(Ok(hostname)) = hostname::get().map(|h| h.into_string()) {
+        tags.push(Tag::new(constants::TRACER_HOSTNAME_TAG_KEY, hostname));
+    }
+
+    #[cfg(not(target_os = "android"))]
+    if let Ok(local_ip_address) = local_ip_address::local_ip().map(|h| h.to_string()) {
+        tags.push(Tag::new(constants::TRACER_IP_TAG_KEY, local_ip_address));
+    }
+
+    jaeger::Process {
+        service_name: service_name.to_owned(),
+        tags: tags.iter().map(From::from).collect(),
+    }
+}
+
+#[derive(Debug)]
+struct JaegerReporter<T> {
+    transport: T,
+    process: jaeger::Process,
+    format: BatchFormat,
+    max_packet_size: usize,
+    retry_policy: Option<RetryPolicy>,
+    retry_buffer: Mutex<RetryBuffer>,
+    seq_no: AtomicI64,
+    counters: ReporterCounters,
+    full_queue_dropped_spans: Arc<AtomicU64>,
+    too_large_dropped_spans: AtomicU64,
+    failed_to_emit_spans: AtomicU64,
+}
+impl<T> JaegerReporter<T> {
+    fn new(service_name: &str, format: BatchFormat, transport: T) -> Self {
+        JaegerReporter {
+            transport,
+            process: default_process(service_name),
+            format,
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            retry_policy: None,
+            retry_buffer: Mutex::new(RetryBuffer::default()),
+            seq_no: AtomicI64::new(0),
+            counters: ReporterCounters::default(),
+            full_queue_dropped_spans: Arc::new(AtomicU64::new(0)),
+            too_large_dropped_spans: AtomicU64::new(0),
+            failed_to_emit_spans: AtomicU64::new(0),
+        }
+    }
+    fn set_max_packet_size(&mut self, size: usize) {
+        self.max_packet_size = size;
+    }
+    fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = Some(policy);
+    }
+    fn set_retry_buffer_size(&mut self, size: usize) {
+        let evicted = self
+            .retry_buffer
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .set_capacity(size);
+        for packet in evicted {
+            self.record_overflow(&packet);
+        }
+    }
+    fn set_spool(&mut self, spool: Spool) {
+        self.retry_buffer
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .set_spool(spool);
+    }
+    fn add_service_tag(&mut self, tag: Tag) {
+        self.process.tags.push((&tag).into());
+    }
+    fn record_overflow(&self, evicted: &EncodedPacket) {
+        self.counters.record_retry_buffer_overflow(1, evicted.spans);
+        self.failed_to_emit_spans
+            .fetch_add(evicted.spans as u64, Ordering::Relaxed);
+    }
+    fn client_stats(&self) -> jaeger::ClientStats {
+        jaeger::ClientStats {
+            full_queue_dropped_spans: self.full_queue_dropped_spans.load(Ordering::Relaxed) as i64,
+            too_large_dropped_spans: self.too_large_dropped_spans.load(Ordering::Relaxed) as i64,
+            failed_to_emit_spans: self.failed_to_emit_spans.load(Ordering::Relaxed) as i64,
+        }
+    }
+    fn encode(&self, spans: Vec<jaeger::Span>) -> Result<Vec<u8>> {
+        let batch = jaeger::Batch {
+            process: self.process.clone(),
+            spans,
+            seq_no: Some(self.seq_no.fetch_add(1, Ordering::Relaxed) + 1),
+            stats: Some(self.client_stats()),
+        };
+        track!(self.encode_batch(batch))
+    }
+    fn encode_batch(&self, batch: jaeger::Batch) -> Result<Vec<u8>> {
+        track!(self.format.encode_batch(batch))
+    }
+
+    /// Splits `spans` into packets and encodes them.
+    ///
+    /// The number of the spans dropped because they are too large is returned together.
+    /// The counters of such spans and encoding failures are updated here,
+    /// so that the sync and async reporting paths share the same semantics.
+    fn prepare_packets(&self, spans: Vec<jaeger::Span>) -> Result<(Vec<EncodedPacket>, usize)> {
+        let result = self
+            .split_into_packets(spans)
+            .and_then(|(packets, dropped)| {
+                self.too_large_dropped_spans
+                    .fetch_add(dropped as u64, Ordering::Relaxed);
+                let packets = packets
+                    .into_iter()
+                    .map(|spans| {
+                        Ok(EncodedPacket {
+                            spans: spans.len(),
+                            bytes: track!(self.encode(spans))?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>();
+                Ok((track!(packets)?, dropped))
+            });
+        if result.is_err() {
+            self.counters.record_encode_failure();
+        }
+        track!(result)
+    }
+
+    /// Updates the counters by the final result of sending a packet which has `spans` spans.
+    fn record_send_result(&self, bytes: &[u8], spans: usize, succeeded: bool) {
+        if succeeded {
+            self.counters.record_sent(bytes.len(), spans);
+        } else {
+            self.counters.record_send_failure();
+        }
+    }
+
+    fn split_into_packets(
+        &self,
+        spans: Vec<jaeger::Span>,
+    ) -> Result<(Vec<Vec<jaeger::Span>>, usize)> {
+        // The largest values are used for the counters since their encoded sizes vary.
+        let empty_batch = jaeger::Batch {
+            process: self.process.clone(),
+            spans: Vec::new(),
+            seq_no: Some(i64::MAX),
+            stats: Some(jaeger::ClientStats {
+                full_queue_dropped_spans: i64::MAX,
+                too_large_dropped_spans: i64::MAX,
+                failed_to_emit_spans: i64::MAX,
+            }),
+        };
+        let overhead = track!(self.encode_batch(empty_batch))?.len();
+        track!(split_into_packets(
+            spans,
+            overhead,
+            self.max_packet_size,
+            |span| Ok(track!(self.format.encode_span(span.clone()))?.len())
+        ))
+    }
+}
+impl<T: Transport> JaegerReporter<T> {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(self.report_spans(spans.iter().map(From::from).collect()))
+    }
+    fn report_spans(&self, spans: Vec<jaeger::Span>) -> Result<()> {
+        track!(self.report_spans_until(spans, None))
+    }
+
+    /// Same as `report_spans` except that retrying a packet is given up (i.e., the packet is buffered)
+    /// if the backoff before the next attempt would pass `deadline`.
+    fn report_spans_until(
+        &self,
+        spans: Vec<jaeger::Span>,
+        deadline: Option<Instant>,
+    ) -> Result<()> {
+        let (packets, dropped) = track!(self.prepare_packets(spans))?;
+        let mut result = track!(self.resend_buffered_packets());
+        for packet in packets {
+            if !self.retry_buffer().is_empty() {
+                // The transport has not recovered yet.
+                self.buffer_packet(packet);
+                continue;
+            }
+            if let Err(e) = track!(self.send_packet(&packet.bytes, packet.spans, deadline)) {
+                self.buffer_packet(packet);
+                result = Err(e);
+            }
+        }
+        track!(check_too_large_spans(dropped, self.max_packet_size))?;
+        result
+    }
+
+    /// Re-sends the packets in the retry buffer (and the spool) in the order they were buffered.
+    fn resend_buffered_packets(&self) -> Result<()> {
+        let mut buffer = self.retry_buffer();
+        while let Some(packet) = buffer.front() {
+            track!(self.send_once(&packet.bytes, packet.spans))?;
+            buffer.pop_front();
+        }
+        if let Some(spool) = buffer.spool_mut() {
+            while let Some(packet) = track!(spool.peek())? {
+                track!(self.send_once(&packet.bytes, packet.spans))?;
+                track!(spool.consume(&packet))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-sends the buffered packets until all of them are sent or `deadline` passes.
+    ///
+    /// Returns the number of the spans left in the in-memory retry buffer.
+    /// The spans in the spool are not counted since they can be replayed later.
+    fn resend_buffered_packets_until(&self, deadline: Instant) -> usize {
+        loop {
+            if self.resend_buffered_packets().is_ok() {
+                return 0;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return self.retry_buffer().buffered_spans();
+            }
+            thread::sleep(std::cmp::min(deadline - now, RESEND_INTERVAL));
+        }
+    }
+
+    /// Returns the number of the spans which have been given up to be delivered.
+    fn undelivered_spans(&self) -> u64 {
+        self.too_large_dropped_spans.load(Ordering::Relaxed)
+            + self.failed_to_emit_spans.load(Ordering::Relaxed)
+    }
+    fn send_once(&self, bytes: &[u8], spans: usize) -> Result<()> {
+        let result = self.transport.send(bytes);
+        self.record_send_result(bytes, spans, result.is_ok());
+        track!(result)
+    }
+    fn send_packet(&self, bytes: &[u8], spans: usize, deadline: Option<Instant>) -> Result<()> {
+        let mut retry = 0;
+        loop {
+            match self.transport.send(bytes) {
+                Ok(()) => {
+                    self.record_send_result(bytes, spans, true);
+                    return Ok(());
+                }
+                Err(e) => {
+                    let backoff = match &self.retry_policy {
+                        Some(policy) if retry + 1 < policy.attempts() => policy.backoff(retry),
+                        _ => {
+                            self.record_send_result(bytes, spans, false);
+                            return Err(track!(e));
+                        }
+                    };
+                    if deadline.is_some_and(|deadline| Instant::now() + backoff > deadline) {
+                        self.record_send_result(bytes, spans, false);
+                        return Err(track!(e));
+                    }
+                    thread::sleep(backoff);
+                    retry += 1;
+                }
+            }
+        }
+    }
+    fn buffer_packet(&self, packet: EncodedPacket) {
+        let mut buffer = self.retry_buffer();
+        if let Some(spool) = buffer.spool_mut() {
+            // If it fails to write the spool, the in-memory buffer is used instead.
+            if let Ok(dropped) = spool.append(&packet.bytes, packet.spans) {
+                self.counters.record_retry_buffer_overflow(0, dropped);
+                self.failed_to_emit_spans
+                    .fetch_add(dropped as u64, Ordering::Relaxed);
+                return;
+            }
+        }
+        if buffer.capacity() == 0 {
+            self.failed_to_emit_spans
+                .fetch_add(packet.spans as u64, Ordering::Relaxed);
+        } else if let Some(evicted) = buffer.push(packet) {
+            self.record_overflow(&evicted);
+        }
+    }
+    fn retry_buffer(&self) -> MutexGuard<'_, RetryBuffer> {
+        self.retry_buffer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Returns the path of a new temporary directory (for testing).
+///
+/// The directory itself is not created.
+#[cfg(test)]
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "rustracing_jaeger_{}_{}_{}",
+        name,
+        std::process::id(),
+        rand::random::<u32>()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use std::net::UdpSocket;
+    use std::sync::atomic::AtomicBool;
+    use thrift_codec::data::Data;
+    use thrift_codec::CompactDecode;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn oversized_batch_is_split_into_packets() -> TestResult {
+        let agent = track_any_err!(UdpSocket::bind("127.0.0.1:0"))?;
+        track_any_err!(agent.set_read_timeout(Some(Duration::from_secs(5))))?;
+
+        let mut reporter = track!(JaegerCompactReporter::new("split_test"))?;
+        reporter.set_agent_addr(track_any_err!(agent.local_addr())?);
+        reporter.set_max_packet_size(2000);
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        for i in 0..10 {
+            let value = if i == 5 { 3000 } else { 300 };
+            let _span = tracer
+                .span("split")
+                .tag(Tag::new("payload", "x".repeat(value)))
+                .start();
+        }
+        let spans = span_rx.try_iter().collect::<Vec<_>>();
+        assert!(reporter.report(&spans).is_err());
+        assert_eq!(
+            reporter.0.too_large_dropped_spans.load(Ordering::Relaxed),
+            1
+        );
+
+        let mut buf = [0; 65536];
+        let mut packets = 0;
+        let mut total_size = 0;
+        while total_size < 9 * 300 {
+            let size = track_any_err!(agent.recv(&mut buf))?;
+            assert!(size <= 2000);
+            packets += 1;
+            total_size += size;
+        }
+        assert!(packets > 1);
+        Ok(())
+    }
+
+    #[derive(Debug, Default)]
+    struct FlakyTransport {
+        down: AtomicBool,
+        attempts: AtomicU64,
+        packets: Mutex<Vec<Vec<u8>>>,
+    }
+    impl Transport for &FlakyTransport {
+        fn send(&self, packet: &[u8]) -> Result<()> {
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            if self.down.load(Ordering::SeqCst) {
+                track_panic!(ErrorKind::Other, "down");
+            }
+            self.packets.lock().unwrap().push(packet.to_owned());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn retry_buffer_works() -> TestResult {
+        let transport = FlakyTransport::default();
+        transport.down.store(true, Ordering::SeqCst);
+
+        let mut reporter = JaegerCompactReporter::with_transport("retry_test", &transport);
+        reporter.set_retry_policy(RetryPolicy::new().max_attempts(2));
+        reporter.set_retry_buffer_size(2);
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        for _ in 0..3 {
+            tracer.span("retry").start();
+            assert!(reporter
+                .report(&span_rx.try_iter().collect::<Vec<_>>())
+                .is_err());
+        }
+        assert_eq!(transport.attempts.load(Ordering::SeqCst), 2 + 1 + 1);
+        assert_eq!(reporter.0.retry_buffer().len(), 2);
+        assert_eq!(reporter.stats().retry_buffer_overflows, 1);
+        assert_eq!(reporter.stats().retry_buffer_dropped_spans, 1);
+        assert_eq!(reporter.0.failed_to_emit_spans.load(Ordering::SeqCst), 1);
+
+        // Recovered
+        transport.down.store(false, Ordering::SeqCst);
+        tracer.span("retry").start();
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+        assert_eq!(transport.packets.lock().unwrap().len(), 3);
+        assert_eq!(reporter.0.retry_buffer().len(), 0);
+
+        let stats = reporter.stats();
+        assert_eq!(stats.spans_sent, 3);
+        assert_eq!(stats.batches_sent, 3);
+        assert_eq!(
+            stats.bytes_written,
+            transport
+                .packets
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|p| p.len() as u64)
+                .sum::<u64>()
+        );
+        assert_eq!(stats.encode_failures, 0);
+        assert_eq!(stats.send_failures, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn batch_seq_no_and_stats_work() -> TestResult {
+        let transport = FlakyTransport::default();
+        let mut reporter = JaegerCompactReporter::with_transport("stats_test", &transport);
+        reporter.set_max_packet_size(1000);
+        reporter
+            .0
+            .full_queue_dropped_spans
+            .store(3, Ordering::SeqCst);
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer
+            .span("stats")
+            .tag(Tag::new("payload", "x".repeat(2000)))
+            .start();
+        tracer.span("stats").start();
+        assert!(reporter
+            .report(&span_rx.try_iter().collect::<Vec<_>>())
+            .is_err());
+        tracer.span("stats").start();
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let packets = transport.packets.lock().unwrap();
+        assert_eq!(packets.len(), 2);
+        for (i, packet) in packets.iter().enumerate() {
+            let message = track_any_err!(Message::compact_decode(&mut &packet[..]))?;
+            let batch = match message.body().fields()[0].data() {
+                Data::Struct(batch) => batch.clone(),
+                data => panic!("unexpected data: {:?}", data),
+            };
+            assert_eq!(batch.fields()[2].data(), &Data::I64(i as i64 + 1));
+            assert_eq!(
+                batch.fields()[3].data(),
+                &Data::Struct(Struct::from((3i64, 1i64, 0i64)))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn agent_host_works() -> TestResult {
+        let agent = match UdpSocket::bind("[::1]:0") {
+            Ok(agent) => agent,
+            Err(_) => return Ok(()), // IPv6 is not available
+        };
+        track_any_err!(agent.set_read_timeout(Some(Duration::from_secs(5))))?;
+        let port = track_any_err!(agent.local_addr())?.port();
+
+        let mut reporter = track!(JaegerCompactReporter::new("agent_host_test"))?;
+        track!(reporter.set_agent_host(&format!("[::1]:{}", port)))?;
+        assert!(track!(reporter.transport().local_addr())?.is_ipv6());
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        {
+            let _span = tracer.span("agent_host").start();
+        }
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+        let mut buf = [0; 65536];
+        track_any_err!(agent.recv(&mut buf))?;
+
+        assert!(reporter.set_agent_host("localhost").is_err()); // no port
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unreachable_agent_is_detected_in_connected_mode() -> TestResult {
+        let agent_addr = {
+            let agent = track_any_err!(UdpSocket::bind("127.0.0.1:0"))?;
+            track_any_err!(agent.local_addr())?
+        };
+
+        let mut reporter = track!(JaegerBinaryReporter::new("connected_test"))?;
+        reporter.set_agent_addr(agent_addr);
+        track!(reporter.set_connected_mode(true))?;
+        assert!(reporter.is_agent_healthy());
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        {
+            let _span = tracer.span("connected").start();
+        }
+        let spans = span_rx.try_iter().collect::<Vec<_>>();
+        let mut last_error = None;
+        for _ in 0..10 {
+            if let Err(e) = reporter.report(&spans) {
+                last_error = Some(e);
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let e = track_assert_some!(last_error, trackable::error::Failed);
+        assert!(is_agent_unreachable(&e));
+        assert!(!reporter.is_agent_healthy());
+        Ok(())
+    }
+}
@@ -0,0 +1,391 @@
+use super::stats::{ReporterCounters, ReporterStats};
+use super::{HttpTransport, Reporter, Transport};
+use crate::json::{self, Json};
+use crate::proto::otlp::{self, any_value, ExportTraceServiceRequest};
+use crate::span::FinishedSpan;
+use crate::thrift::jaeger;
+use crate::Result;
+use prost::Message;
+use rustracing::tag::Tag;
+use std::time::Duration;
+
+/// The name of the instrumentation scope of the exported spans.
+const SCOPE_NAME: &str = "rustracing_jaeger";
+
+/// Encoding of the requests sent by `OtlpHttpReporter`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OtlpEncoding {
+    /// Binary protobuf encoding (`Content-Type: application/x-protobuf`).
+    #[default]
+    Protobuf,
+
+    /// JSON protobuf encoding (`Content-Type: application/json`).
+    Json,
+}
+impl OtlpEncoding {
+    fn content_type(self) -> &'static str {
+        match self {
+            OtlpEncoding::Protobuf => "application/x-protobuf",
+            OtlpEncoding::Json => "application/json",
+        }
+    }
+}
+
+/// Reporter which exports spans to an OpenTelemetry collector over OTLP/HTTP
+/// (i.e., `/v1/traces`).
+///
+/// The spans are converted to OTLP `ResourceSpans` as follows:
+/// - The parent reference becomes `parent_span_id`, and the other references become links
+/// - The `span.kind` tag becomes `kind`, and the `error` tag sets the status to `ERROR`
+/// - Tags (including the debug id) become attributes, and logs become events
+/// - The service name and the process tags become the resource attributes
+///
+/// # Examples
+///
+/// ```no_run
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::{OtlpEncoding, OtlpHttpReporter};
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// let spans = span_rx.try_iter().collect::<Vec<_>>();
+///
+/// let mut reporter =
+///     OtlpHttpReporter::new("sample_service", "http://otel-collector:4318/v1/traces").unwrap();
+/// reporter.set_encoding(OtlpEncoding::Json);
+/// reporter.report(&spans).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct OtlpHttpReporter {
+    transport: HttpTransport,
+    process: jaeger::Process,
+    encoding: OtlpEncoding,
+    counters: ReporterCounters,
+}
+impl OtlpHttpReporter {
+    /// Makes a new `OtlpHttpReporter` instance which sends spans to `endpoint_url`
+    /// (e.g., `"http://127.0.0.1:4318/v1/traces"`).
+    ///
+    /// # Errors
+    ///
+    /// If `endpoint_url` is not a valid `http://` URL,
+    /// this function will return an error which has the kind `ErrorKind::InvalidInput`.
+    pub fn new(service_name: &str, endpoint_url: &str) -> Result<Self> {
+        let mut transport = track!(HttpTransport::new(endpoint_url))?;
+        let encoding = OtlpEncoding::default();
+        transport.set_content_type(encoding.content_type());
+        Ok(OtlpHttpReporter {
+            transport,
+            process: super::default_process(service_name),
+            encoding,
+            counters: ReporterCounters::default(),
+        })
+    }
+
+    /// Returns a reference to the transport of this reporter.
+    pub fn transport(&self) -> &HttpTransport {
+        &self.transport
+    }
+
+    /// Returns the snapshot of the statistics of this reporter.
+    pub fn stats(&self) -> ReporterStats {
+        self.counters.snapshot()
+    }
+
+    /// Sets the encoding of the requests.
+    ///
+    /// The default value is `OtlpEncoding::Protobuf`.
+    pub fn set_encoding(&mut self, encoding: OtlpEncoding) {
+        self.encoding = encoding;
+        self.transport.set_content_type(encoding.content_type());
+    }
+
+    /// Adds the HTTP header `name: value` to each request (e.g., for authentication).
+    ///
+    /// # Errors
+    ///
+    /// If `name` is not a valid header name, `name` is one of the headers written by the transport itself
+    /// (i.e., `Host`, `Content-Length`, `Connection` and `Transfer-Encoding`) or `value` contains CR, LF or NUL,
+    /// this method will return an error which has the kind `ErrorKind::InvalidInput`.
+    pub fn set_header(&mut self, name: &str, value: &str) -> Result<()> {
+        track!(self.transport.set_header(name, value))
+    }
+
+    /// Sets the timeout of each of connecting, sending a request and receiving the response.
+    ///
+    /// The default value is `Duration::from_secs(5)`.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.transport.set_timeout(timeout);
+    }
+
+    /// Adds `tag` to this service (i.e., to the resource attributes).
+    pub fn add_service_tag(&mut self, tag: Tag) {
+        self.process.tags.push((&tag).into());
+    }
+
+    /// Reports `spans`.
+    ///
+    /// All of `spans` are sent by a single request.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to send the request or the collector responds with a non-2xx status,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        let request = self.export_request(spans);
+        let bytes = match self.encoding {
+            OtlpEncoding::Protobuf => request.encode_to_vec(),
+            OtlpEncoding::Json => to_json(&request).to_string().into_bytes(),
+        };
+        match self.transport.send(&bytes) {
+            Ok(()) => {
+                self.counters.record_sent(bytes.len(), spans.len());
+                Ok(())
+            }
+            Err(e) => {
+                self.counters.record_send_failure();
+                Err(track!(e))
+            }
+        }
+    }
+
+    fn export_request(&self, spans: &[FinishedSpan]) -> ExportTraceServiceRequest {
+        let scope_spans = otlp::ScopeSpans {
+            scope: Some(otlp::InstrumentationScope {
+                name: SCOPE_NAME.to_owned(),
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+                attributes: Vec::new(),
+                dropped_attributes_count: 0,
+            }),
+            spans: spans
+                .iter()
+                .map(|span| otlp::Span::from(jaeger::Span::from(span)))
+                .collect(),
+            schema_url: String::new(),
+        };
+        ExportTraceServiceRequest {
+            resource_spans: vec![otlp::ResourceSpans {
+                resource: Some(self.process.clone().into()),
+                scope_spans: vec![scope_spans],
+                schema_url: String::new(),
+            }],
+        }
+    }
+}
+impl Reporter for OtlpHttpReporter {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(OtlpHttpReporter::report(self, spans))
+    }
+}
+
+/// Converts `request` to the OTLP/JSON representation.
+///
+/// Following the OTLP specification, ids are hex strings (not base64) and 64-bit integers are strings.
+/// Fields which have the default values are omitted.
+fn to_json(request: &ExportTraceServiceRequest) -> Json {
+    let resource_spans = request
+        .resource_spans
+        .iter()
+        .map(|rs| {
+            let mut json = Json::object();
+            if let Some(resource) = &rs.resource {
+                json.insert(
+                    "resource",
+                    Json::object().with("attributes", attributes_to_json(&resource.attributes)),
+                );
+            }
+            let scope_spans = rs
+                .scope_spans
+                .iter()
+                .map(|ss| {
+                    let mut json = Json::object();
+                    if let Some(scope) = &ss.scope {
+                        json.insert(
+                            "scope",
+                            Json::object()
+                                .with("name", scope.name.as_str())
+                                .with("version", scope.version.as_str()),
+                        );
+                    }
+                    json.with(
+                        "spans",
+                        ss.spans.iter().map(span_to_json).collect::<Vec<_>>(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            json.with("scopeSpans", scope_spans)
+        })
+        .collect::<Vec<_>>();
+    Json::object().with("resourceSpans", resource_spans)
+}
+
+fn span_to_json(span: &otlp::Span) -> Json {
+    let mut json = Json::object()
+        .with("traceId", hex(&span.trace_id))
+        .with("spanId", hex(&span.span_id));
+    if !span.parent_span_id.is_empty() {
+        json.insert("parentSpanId", hex(&span.parent_span_id));
+    }
+    json = json
+        .with("flags", i64::from(span.flags))
+        .with("name", span.name.as_str())
+        .with("kind", i64::from(span.kind))
+        .with("startTimeUnixNano", span.start_time_unix_nano.to_string())
+        .with("endTimeUnixNano", span.end_time_unix_nano.to_string());
+    if !span.attributes.is_empty() {
+        json.insert("attributes", attributes_to_json(&span.attributes));
+    }
+    if !span.events.is_empty() {
+        let events = span
+            .events
+            .iter()
+            .map(|e| {
+                Json::object()
+                    .with("timeUnixNano", e.time_unix_nano.to_string())
+                    .with("name", e.name.as_str())
+                    .with("attributes", attributes_to_json(&e.attributes))
+            })
+            .collect::<Vec<_>>();
+        json.insert("events", events);
+    }
+    if !span.links.is_empty() {
+        let links = span
+            .links
+            .iter()
+            .map(|l| {
+                Json::object()
+                    .with("traceId", hex(&l.trace_id))
+                    .with("spanId", hex(&l.span_id))
+                    .with("attributes", attributes_to_json(&l.attributes))
+            })
+            .collect::<Vec<_>>();
+        json.insert("links", links);
+    }
+    if let Some(status) = &span.status {
+        let mut s = Json::object().with("code", i64::from(status.code));
+        if !status.message.is_empty() {
+            s.insert("message", status.message.as_str());
+        }
+        json.insert("status", s);
+    }
+    json
+}
+
+fn attributes_to_json(attributes: &[otlp::KeyValue]) -> Json {
+    use self::any_value::Value;
+    attributes
+        .iter()
+        .map(|kv| {
+            let value = match kv.value.as_ref().and_then(|v| v.value.as_ref()) {
+                None => Json::object(),
+                Some(Value::StringValue(v)) => Json::object().with("stringValue", v.as_str()),
+                Some(Value::BoolValue(v)) => Json::object().with("boolValue", *v),
+                Some(Value::IntValue(v)) => Json::object().with("intValue", v.to_string()),
+                Some(Value::DoubleValue(v)) => Json::object().with("doubleValue", *v),
+                Some(Value::BytesValue(v)) => Json::object().with("bytesValue", json::base64(v)),
+            };
+            Json::object()
+                .with("key", kv.key.as_str())
+                .with("value", value)
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::test_server;
+    use crate::span::{SpanContextStateBuilder, TraceId};
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn protobuf_encoding_works() -> TestResult {
+        let (addr, rx) = track!(test_server::start(200))?;
+        let url = format!("http://{}/v1/traces", addr);
+        let mut reporter = track!(OtlpHttpReporter::new("otlp_test", &url))?;
+        reporter.add_service_tag(Tag::new("foo", "bar"));
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        tracer.span("bar").start();
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let request = track_any_err!(rx.recv_timeout(Duration::from_secs(5)))?;
+        assert_eq!(request.path, "/v1/traces");
+        assert_eq!(
+            request.header("content-type"),
+            Some("application/x-protobuf")
+        );
+
+        let request = track_any_err!(ExportTraceServiceRequest::decode(&request.body[..]))?;
+        let resource_spans = &request.resource_spans[0];
+        let resource = resource_spans.resource.as_ref().unwrap();
+        assert_eq!(
+            resource.attributes[0],
+            otlp::KeyValue::new(otlp::SERVICE_NAME_KEY, "otlp_test".into())
+        );
+        assert!(resource
+            .attributes
+            .contains(&otlp::KeyValue::new("foo", "bar".into())));
+        let spans = &resource_spans.scope_spans[0].spans;
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].name, "foo");
+        assert_eq!(reporter.stats().spans_sent, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn json_encoding_works() -> TestResult {
+        let (addr, rx) = track!(test_server::start(200))?;
+        let url = format!("http://{}/v1/traces", addr);
+        let mut reporter = track!(OtlpHttpReporter::new("otlp_test", &url))?;
+        reporter.set_encoding(OtlpEncoding::Json);
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        let state = SpanContextStateBuilder::new()
+            .trace_id(TraceId {
+                high: 0,
+                low: 0xabc,
+            })
+            .span_id(0x10)
+            .finish();
+        tracer
+            .span("foo")
+            .tag(Tag::new("n", 1))
+            .start_with_state(state);
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let request = track_any_err!(rx.recv_timeout(Duration::from_secs(5)))?;
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        let body = track_any_err!(String::from_utf8(request.body))?;
+        assert!(body.starts_with(r#"{"resourceSpans":[{"resource":{"attributes":[{"key":"service.name","value":{"stringValue":"otlp_test"}}"#));
+        assert!(body.contains(r#""traceId":"00000000000000000000000000000abc","spanId":"0000000000000010","flags":1,"name":"foo","kind":1,"#));
+        assert!(body.contains(r#""attributes":[{"key":"n","value":{"intValue":"1"}}]"#));
+        Ok(())
+    }
+
+    #[test]
+    fn error_status_is_reported() -> TestResult {
+        let (addr, _rx) = track!(test_server::start(503))?;
+        let url = format!("http://{}/v1/traces", addr);
+        let reporter = track!(OtlpHttpReporter::new("otlp_test", &url))?;
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        assert!(reporter
+            .report(&span_rx.try_iter().collect::<Vec<_>>())
+            .is_err());
+        assert_eq!(reporter.stats().send_failures, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,651 @@
+use super::{
+    HttpTransport, JaegerBinaryReporter, JaegerCompactReporter, JaegerHttpReporter, JaegerReporter,
+    Transport, UdpTransport,
+};
+use crate::span::{SpanReceiver, FLAG_DEBUG};
+use crate::thrift::jaeger;
+use crate::Result;
+use crossbeam_channel::{self as channel, Receiver, Sender};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// `RemoteReporter` builder.
+#[derive(Debug)]
+pub struct RemoteReporterBuilder<T = UdpTransport> {
+    reporter: JaegerReporter<T>,
+    span_rx: SpanReceiver,
+    queue_size: usize,
+    priority_policy: PriorityPolicy,
+    max_batch_size: usize,
+    flush_interval: Duration,
+}
+impl<T> RemoteReporterBuilder<T>
+where
+    T: Transport + Send + 'static,
+{
+    /// Makes a new `RemoteReporterBuilder` which reports the spans received from `span_rx`
+    /// via the given `JaegerCompactReporter`.
+    pub fn compact(reporter: JaegerCompactReporter<T>, span_rx: SpanReceiver) -> Self {
+        Self::new(reporter.0, span_rx)
+    }
+
+    /// Makes a new `RemoteReporterBuilder` which reports the spans received from `span_rx`
+    /// via the given `JaegerBinaryReporter`.
+    pub fn binary(reporter: JaegerBinaryReporter<T>, span_rx: SpanReceiver) -> Self {
+        Self::new(reporter.0, span_rx)
+    }
+
+    fn new(reporter: JaegerReporter<T>, span_rx: SpanReceiver) -> Self {
+        RemoteReporterBuilder {
+            reporter,
+            span_rx,
+            queue_size: 1000,
+            priority_policy: PriorityPolicy::DebugFirst,
+            max_batch_size: 100,
+            flush_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the maximum number of spans waiting to be reported.
+    ///
+    /// If the queue is full, newly received spans are discarded (see `priority_policy` for the exception).
+    /// The number of the discarded spans is reported to the agent as `fullQueueDroppedSpans`.
+    ///
+    /// The default value is `1000`.
+    pub fn queue_size(mut self, size: usize) -> Self {
+        self.queue_size = size;
+        self
+    }
+
+    /// Sets the policy for prioritizing queued spans.
+    ///
+    /// The default value is `PriorityPolicy::DebugFirst`.
+    pub fn priority_policy(mut self, policy: PriorityPolicy) -> Self {
+        self.priority_policy = policy;
+        self
+    }
+
+    /// Sets the maximum number of spans included in a batch.
+    ///
+    /// When the number of queued spans reaches this value, they are reported immediately
+    /// without waiting for the flush interval.
+    ///
+    /// The default value is `100`.
+    pub fn max_batch_size(mut self, size: usize) -> Self {
+        self.max_batch_size = size;
+        self
+    }
+
+    /// Sets the interval at which queued spans are reported.
+    ///
+    /// The default value is `Duration::from_secs(1)`.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Spawns the worker threads and returns a `RemoteReporter` handle.
+    pub fn finish(self) -> RemoteReporter {
+        let max_batch_size = std::cmp::max(1, self.max_batch_size);
+        let queue = Arc::new(SpanQueue::new(
+            self.queue_size,
+            self.priority_policy,
+            Arc::clone(&self.reporter.full_queue_dropped_spans),
+        ));
+        let (command_tx, command_rx) = channel::unbounded();
+        let (signal_tx, signal_rx) = channel::unbounded();
+
+        let intake = Intake::new(
+            self.span_rx,
+            command_rx,
+            signal_tx,
+            Arc::clone(&queue),
+            max_batch_size,
+        );
+        let dispatcher = Dispatcher {
+            reporter: self.reporter,
+            signal_rx,
+            queue,
+            max_batch_size,
+            flush_interval: self.flush_interval,
+        };
+        let threads = vec![
+            thread::spawn(move || intake.run()),
+            thread::spawn(move || dispatcher.run()),
+        ];
+        RemoteReporter {
+            command_tx,
+            threads,
+        }
+    }
+}
+
+impl RemoteReporterBuilder<HttpTransport> {
+    /// Makes a new `RemoteReporterBuilder` which reports the spans received from `span_rx`
+    /// via the given `JaegerHttpReporter`.
+    pub fn http(reporter: JaegerHttpReporter, span_rx: SpanReceiver) -> Self {
+        Self::new(reporter.0, span_rx)
+    }
+}
+
+/// Policy for prioritizing the spans waiting to be reported by `RemoteReporter` (or `AsyncReporter`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PriorityPolicy {
+    /// Spans are reported in the order they are received.
+    ///
+    /// If the queue is full, newly received spans are discarded regardless of their flags.
+    Fifo,
+
+    /// Spans which have the debug flag (see `SpanContextStateBuilder::debug_id`) are reported
+    /// before the ordinary ones.
+    ///
+    /// If the queue is full when a debug span is received,
+    /// the oldest ordinary span is discarded to make room for it.
+    #[default]
+    DebugFirst,
+}
+
+/// Reporter which reports spans received from a `SpanReceiver` in background threads.
+///
+/// Received spans are queued and reported in batches, either when the number of queued spans
+/// reaches the maximum batch size or when the flush interval elapses.
+///
+/// When the last handle of the associated `Tracer` is dropped, or `RemoteReporter::stop` is called,
+/// the remaining spans are reported and the worker threads exit.
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::{JaegerCompactReporter, RemoteReporterBuilder};
+///
+/// let (span_tx, span_rx) = crossbeam_channel::bounded(10);
+/// let tracer = Tracer::with_sender(AllSampler, span_tx);
+///
+/// let reporter = JaegerCompactReporter::new("sample_service").unwrap();
+/// let reporter = RemoteReporterBuilder::compact(reporter, span_rx).finish();
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// reporter.flush().unwrap();
+/// reporter.stop();
+/// ```
+#[derive(Debug)]
+pub struct RemoteReporter {
+    command_tx: Sender<Command>,
+    threads: Vec<JoinHandle<()>>,
+}
+impl RemoteReporter {
+    /// Reports all the spans received so far and waits for the completion.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to report some of the spans,
+    /// this method will return the last error occurred during the flush.
+    pub fn flush(&self) -> Result<()> {
+        let (reply_tx, reply_rx) = channel::bounded(1);
+        if self.command_tx.send(Command::Flush(reply_tx)).is_err() {
+            // The worker threads have already finished.
+            return Ok(());
+        }
+        match reply_rx.recv() {
+            Err(_) => Ok(()),
+            Ok(result) => track!(result),
+        }
+    }
+
+    /// Reports the remaining spans and stops the worker threads.
+    pub fn stop(mut self) {
+        self.stop_threads();
+    }
+
+    /// Stops receiving spans and reports the pending ones until all of them are sent
+    /// or `deadline` passes.
+    ///
+    /// Returns the number of the spans abandoned because they could not be delivered by the deadline,
+    /// including the ones dropped or failed to be sent during the shutdown.
+    /// Spans stored in the spool are not regarded as abandoned.
+    ///
+    /// Note that spans sent to the `SpanReceiver` after this method is called are not reported.
+    pub fn shutdown(mut self, deadline: Instant) -> usize {
+        let (reply_tx, reply_rx) = channel::bounded(1);
+        let _ = self.command_tx.send(Command::Shutdown(deadline, reply_tx));
+        let abandoned = reply_rx.recv().unwrap_or(0);
+        self.stop_threads();
+        abandoned
+    }
+
+    fn stop_threads(&mut self) {
+        let _ = self.command_tx.send(Command::Stop);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+impl Drop for RemoteReporter {
+    fn drop(&mut self) {
+        self.stop_threads();
+    }
+}
+
+#[derive(Debug)]
+enum Command {
+    Flush(Sender<Result<()>>),
+    Shutdown(Instant, Sender<usize>),
+    Stop,
+}
+
+impl IntakeCommand for Command {
+    fn stop() -> Self {
+        Command::Stop
+    }
+
+    fn is_stop(&self) -> bool {
+        matches!(self, Command::Stop | Command::Shutdown(..))
+    }
+}
+
+/// Signal sent from `Intake` to the worker which reports queued spans.
+#[derive(Debug)]
+pub(super) enum Signal<C> {
+    BatchReady,
+    Command(C),
+}
+
+/// Command sent from a reporter handle to its worker via `Intake`.
+pub(super) trait IntakeCommand {
+    /// Returns the command which makes the worker report the remaining spans and exit.
+    fn stop() -> Self;
+
+    /// Returns `true` if the worker exits after handling this command.
+    fn is_stop(&self) -> bool;
+}
+
+/// Channel via which `Intake` sends signals to the worker.
+pub(super) trait SignalSender<C> {
+    /// Returns `false` if the worker has already finished.
+    fn send_signal(&self, signal: Signal<C>) -> bool;
+}
+impl<C> SignalSender<C> for Sender<Signal<C>> {
+    fn send_signal(&self, signal: Signal<C>) -> bool {
+        self.send(signal).is_ok()
+    }
+}
+
+/// Queue of the spans waiting to be reported (shared with `AsyncReporter`).
+#[derive(Debug)]
+pub(super) struct SpanQueue {
+    spans: Mutex<QueuedSpans>,
+    capacity: usize,
+    priority_policy: PriorityPolicy,
+    dropped_spans: Arc<AtomicU64>,
+}
+impl SpanQueue {
+    pub(super) fn new(
+        capacity: usize,
+        priority_policy: PriorityPolicy,
+        dropped_spans: Arc<AtomicU64>,
+    ) -> Self {
+        SpanQueue {
+            spans: Mutex::new(QueuedSpans::default()),
+            capacity,
+            priority_policy,
+            dropped_spans,
+        }
+    }
+
+    /// Pushes `span` and returns the resulting queue length,
+    /// or `None` if the span is discarded because the queue is full.
+    pub(super) fn push(&self, span: jaeger::Span) -> Option<usize> {
+        let mut spans = self.lock();
+        let is_debug = span.flags & i32::from(FLAG_DEBUG) != 0;
+        let prioritized = is_debug && self.priority_policy == PriorityPolicy::DebugFirst;
+        if spans.len() >= self.capacity {
+            // Evicts the oldest ordinary span to make room for the debug span.
+            if !prioritized || spans.normal.pop_front().is_none() {
+                self.dropped_spans.fetch_add(1, Ordering::Relaxed);
+                return None;
+            }
+            self.dropped_spans.fetch_add(1, Ordering::Relaxed);
+        }
+        if prioritized {
+            spans.debug.push_back(span);
+        } else {
+            spans.normal.push_back(span);
+        }
+        Some(spans.len())
+    }
+
+    /// Pops at most `max_batch_size` spans (debug spans first).
+    pub(super) fn pop_batch(&self, max_batch_size: usize) -> Vec<jaeger::Span> {
+        let mut spans = self.lock();
+        let n = std::cmp::min(spans.debug.len(), max_batch_size);
+        let mut batch = spans.debug.drain(..n).collect::<Vec<_>>();
+        let n = std::cmp::min(spans.normal.len(), max_batch_size - batch.len());
+        batch.extend(spans.normal.drain(..n));
+        batch
+    }
+
+    /// Discards all the queued spans and returns the number of them.
+    fn clear(&self) -> usize {
+        let mut spans = self.lock();
+        let n = spans.len();
+        spans.debug.clear();
+        spans.normal.clear();
+        n
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueuedSpans> {
+        self.spans.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueuedSpans {
+    debug: VecDeque<jaeger::Span>,
+    normal: VecDeque<jaeger::Span>,
+}
+impl QueuedSpans {
+    fn len(&self) -> usize {
+        self.debug.len() + self.normal.len()
+    }
+}
+
+/// Moves received spans to the queue (shared with `AsyncReporter`).
+///
+/// Commands are forwarded to the worker after the spans received before them are queued.
+#[derive(Debug)]
+pub(super) struct Intake<C, S> {
+    span_rx: SpanReceiver,
+    command_rx: Receiver<C>,
+    signal_tx: S,
+    queue: Arc<SpanQueue>,
+    max_batch_size: usize,
+}
+impl<C, S> Intake<C, S>
+where
+    C: IntakeCommand,
+    S: SignalSender<C>,
+{
+    pub(super) fn new(
+        span_rx: SpanReceiver,
+        command_rx: Receiver<C>,
+        signal_tx: S,
+        queue: Arc<SpanQueue>,
+        max_batch_size: usize,
+    ) -> Self {
+        Intake {
+            span_rx,
+            command_rx,
+            signal_tx,
+            queue,
+            max_batch_size,
+        }
+    }
+
+    pub(super) fn run(self) {
+        loop {
+            channel::select! {
+                recv(self.span_rx) -> span => match span {
+                    Ok(span) => self.enqueue(span),
+                    Err(_) => break,
+                },
+                recv(self.command_rx) -> command => {
+                    let command = command.unwrap_or_else(|_| C::stop());
+                    let is_stop = command.is_stop();
+
+                    // Makes sure that the spans sent before the command are included in the flush.
+                    while let Ok(span) = self.span_rx.try_recv() {
+                        self.enqueue(span);
+                    }
+                    if !self.signal_tx.send_signal(Signal::Command(command)) || is_stop {
+                        return;
+                    }
+                }
+            }
+        }
+
+        // All the span senders have been dropped.
+        self.signal_tx.send_signal(Signal::Command(C::stop()));
+    }
+
+    fn enqueue(&self, span: crate::span::FinishedSpan) {
+        if let Some(len) = self.queue.push(jaeger::Span::from(&span)) {
+            if len % self.max_batch_size == 0 {
+                self.signal_tx.send_signal(Signal::BatchReady);
+            }
+        }
+    }
+}
+
+/// Reports queued spans.
+#[derive(Debug)]
+struct Dispatcher<T> {
+    reporter: JaegerReporter<T>,
+    signal_rx: Receiver<Signal<Command>>,
+    queue: Arc<SpanQueue>,
+    max_batch_size: usize,
+    flush_interval: Duration,
+}
+impl<T: Transport> Dispatcher<T> {
+    fn run(self) {
+        let mut deadline = Instant::now() + self.flush_interval;
+        loop {
+            channel::select! {
+                recv(self.signal_rx) -> signal => match signal {
+                    Ok(Signal::BatchReady) => {
+                        while self.queue.len() >= self.max_batch_size {
+                            let batch = self.queue.pop_batch(self.max_batch_size);
+                            let _ = self.reporter.report_spans(batch);
+                        }
+                    }
+                    Ok(Signal::Command(Command::Flush(reply_tx))) => {
+                        let _ = reply_tx.send(self.flush());
+                    }
+                    Ok(Signal::Command(Command::Shutdown(deadline, reply_tx))) => {
+                        let _ = reply_tx.send(self.shutdown(deadline));
+                        return;
+                    }
+                    Ok(Signal::Command(Command::Stop)) | Err(_) => {
+                        let _ = self.flush();
+                        return;
+                    }
+                },
+                recv(channel::at(deadline)) -> _ => {
+                    if self.queue.len() == 0 {
+                        // Gives the packets in the retry buffer a chance to be re-sent.
+                        let _ = self.reporter.resend_buffered_packets();
+                    }
+                    let _ = self.flush();
+                    deadline = Instant::now() + self.flush_interval;
+                }
+            }
+        }
+    }
+
+    fn shutdown(&self, deadline: Instant) -> usize {
+        let undelivered = self.reporter.undelivered_spans();
+        while Instant::now() < deadline {
+            let batch = self.queue.pop_batch(self.max_batch_size);
+            if batch.is_empty() {
+                break;
+            }
+            let _ = self.reporter.report_spans_until(batch, Some(deadline));
+        }
+        let abandoned_in_queue = self.queue.clear();
+        let abandoned_in_buffer = self.reporter.resend_buffered_packets_until(deadline);
+        let dropped = self.reporter.undelivered_spans() - undelivered;
+        abandoned_in_queue + abandoned_in_buffer + dropped as usize
+    }
+
+    fn flush(&self) -> Result<()> {
+        let mut result = Ok(());
+        loop {
+            let batch = self.queue.pop_batch(self.max_batch_size);
+            if batch.is_empty() {
+                return result;
+            }
+            if let Err(e) = track!(self.reporter.report_spans(batch)) {
+                result = Err(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reporter::RetryPolicy;
+    use crate::span::SpanContextStateBuilder;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use std::net::UdpSocket;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn remote_reporter_works() -> TestResult {
+        let agent = track_any_err!(UdpSocket::bind("127.0.0.1:0"))?;
+        track_any_err!(agent.set_read_timeout(Some(Duration::from_secs(5))))?;
+
+        let (span_tx, span_rx) = crossbeam_channel::bounded(10);
+        let tracer = Tracer::with_sender(AllSampler, span_tx);
+
+        let mut reporter = track!(JaegerCompactReporter::new("remote_reporter_test"))?;
+        reporter.set_agent_addr(track_any_err!(agent.local_addr())?);
+        let reporter = RemoteReporterBuilder::compact(reporter, span_rx)
+            .flush_interval(Duration::from_secs(60))
+            .max_batch_size(2)
+            .finish();
+
+        // Reported as soon as the batch becomes full
+        {
+            let _span0 = tracer.span("foo").start();
+            let _span1 = tracer.span("bar").start();
+        }
+        let mut buf = [0; 65536];
+        track_any_err!(agent.recv(&mut buf))?;
+
+        // Reported by an explicit flush
+        {
+            let _span = tracer.span("baz").start();
+        }
+        track!(reporter.flush())?;
+        track_any_err!(agent.recv(&mut buf))?;
+
+        reporter.stop();
+        Ok(())
+    }
+
+    #[test]
+    fn debug_spans_are_prioritized() {
+        let dropped = Arc::new(AtomicU64::new(0));
+        let queue = SpanQueue::new(2, PriorityPolicy::DebugFirst, Arc::clone(&dropped));
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        let debug_state = || {
+            SpanContextStateBuilder::new()
+                .debug_id("foo".to_owned())
+                .finish()
+        };
+        tracer.span("normal0").start();
+        tracer.span("normal1").start();
+        tracer.span("debug0").start_with_state(debug_state());
+        tracer.span("normal2").start();
+        tracer.span("debug1").start_with_state(debug_state());
+        tracer.span("debug2").start_with_state(debug_state());
+        for span in span_rx.try_iter() {
+            queue.push(jaeger::Span::from(&span));
+        }
+        assert_eq!(dropped.load(Ordering::SeqCst), 4);
+
+        let names = queue
+            .pop_batch(10)
+            .into_iter()
+            .map(|s| s.operation_name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["debug0", "debug1"]);
+    }
+
+    #[test]
+    fn shutdown_works() -> TestResult {
+        let agent = track_any_err!(UdpSocket::bind("127.0.0.1:0"))?;
+        track_any_err!(agent.set_read_timeout(Some(Duration::from_secs(5))))?;
+
+        let (span_tx, span_rx) = crossbeam_channel::bounded(10);
+        let tracer = Tracer::with_sender(AllSampler, span_tx);
+
+        let mut reporter = track!(JaegerCompactReporter::new("shutdown_test"))?;
+        reporter.set_agent_addr(track_any_err!(agent.local_addr())?);
+        let reporter = RemoteReporterBuilder::compact(reporter, span_rx)
+            .flush_interval(Duration::from_secs(60))
+            .finish();
+
+        tracer.span("foo").start();
+        tracer.span("bar").start();
+        let abandoned = reporter.shutdown(Instant::now() + Duration::from_secs(5));
+        assert_eq!(abandoned, 0);
+
+        let mut buf = [0; 65536];
+        track_any_err!(agent.recv(&mut buf))?;
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    struct DownTransport;
+    impl Transport for DownTransport {
+        fn send(&self, _packet: &[u8]) -> Result<()> {
+            track_panic!(crate::ErrorKind::Other, "down");
+        }
+    }
+
+    #[test]
+    fn shutdown_reports_abandoned_spans() {
+        let (span_tx, span_rx) = crossbeam_channel::bounded(10);
+        let tracer = Tracer::with_sender(AllSampler, span_tx);
+
+        let mut reporter = JaegerCompactReporter::with_transport("shutdown_test", DownTransport);
+        reporter.set_retry_buffer_size(10);
+        let reporter = RemoteReporterBuilder::compact(reporter, span_rx)
+            .flush_interval(Duration::from_secs(60))
+            .max_batch_size(2)
+            .finish();
+
+        for _ in 0..3 {
+            tracer.span("foo").start();
+        }
+        let abandoned = reporter.shutdown(Instant::now() + Duration::from_millis(200));
+        assert_eq!(abandoned, 3);
+    }
+
+    #[test]
+    fn shutdown_does_not_wait_for_retries_beyond_deadline() {
+        let (span_tx, span_rx) = crossbeam_channel::bounded(10);
+        let tracer = Tracer::with_sender(AllSampler, span_tx);
+
+        let mut reporter = JaegerCompactReporter::with_transport("shutdown_test", DownTransport);
+        reporter.set_retry_policy(
+            RetryPolicy::new()
+                .max_attempts(10)
+                .initial_backoff(Duration::from_secs(10))
+                .max_backoff(Duration::from_secs(10)),
+        );
+        reporter.set_retry_buffer_size(10);
+        let reporter = RemoteReporterBuilder::compact(reporter, span_rx)
+            .flush_interval(Duration::from_secs(60))
+            .finish();
+
+        tracer.span("foo").start();
+        let start = Instant::now();
+        let abandoned = reporter.shutdown(start + Duration::from_millis(200));
+        assert_eq!(abandoned, 1);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
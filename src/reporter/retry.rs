@@ -0,0 +1,186 @@
+use super::Spool;
+use rand::Rng;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Policy for retrying failed sends.
+///
+/// The interval between attempts grows exponentially from `initial_backoff` up to `max_backoff`,
+/// and a random jitter is applied to each interval.
+///
+/// # Examples
+///
+/// ```
+/// use rustracing_jaeger::reporter::{JaegerCompactReporter, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let mut reporter = JaegerCompactReporter::new("sample_service").unwrap();
+/// reporter.set_retry_policy(
+///     RetryPolicy::new()
+///         .max_attempts(5)
+///         .initial_backoff(Duration::from_millis(20)),
+/// );
+/// reporter.set_retry_buffer_size(100);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+impl RetryPolicy {
+    /// Makes a new `RetryPolicy` instance with the default settings.
+    pub fn new() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the maximum number of attempts to send a packet (including the first one).
+    ///
+    /// The default value is `3`.
+    pub fn max_attempts(mut self, n: usize) -> Self {
+        self.max_attempts = n;
+        self
+    }
+
+    /// Sets the interval before the first retry.
+    ///
+    /// The default value is `Duration::from_millis(10)`.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Sets the upper bound of the interval between attempts.
+    ///
+    /// The default value is `Duration::from_secs(1)`.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub(crate) fn attempts(&self) -> usize {
+        std::cmp::max(1, self.max_attempts)
+    }
+
+    /// Returns the interval before the `retry`-th retry (zero origin).
+    pub(crate) fn backoff(&self, retry: usize) -> Duration {
+        let factor = 1u32.checked_shl(retry as u32).unwrap_or(u32::MAX);
+        let backoff = std::cmp::min(
+            self.initial_backoff.saturating_mul(factor),
+            self.max_backoff,
+        );
+
+        // Picks an interval from `[backoff / 2, backoff]` at random.
+        let half = backoff / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bounded buffer of encoded packets which could not be sent.
+///
+/// If a spool is set, it is used to keep packets instead of the in-memory buffer.
+#[derive(Debug, Default)]
+pub(crate) struct RetryBuffer {
+    packets: VecDeque<EncodedPacket>,
+    capacity: usize,
+    spool: Option<Spool>,
+}
+impl RetryBuffer {
+    /// Sets the capacity of the buffer and returns the packets evicted by the change.
+    pub(crate) fn set_capacity(&mut self, capacity: usize) -> Vec<EncodedPacket> {
+        self.capacity = capacity;
+        let n = self.packets.len().saturating_sub(capacity);
+        self.packets.drain(..n).collect()
+    }
+
+    /// Pushes `packet` to the buffer.
+    ///
+    /// If the buffer is full, the oldest packet is evicted and returned.
+    pub(crate) fn push(&mut self, packet: EncodedPacket) -> Option<EncodedPacket> {
+        if self.capacity == 0 {
+            return Some(packet);
+        }
+        let evicted = if self.packets.len() >= self.capacity {
+            self.packets.pop_front()
+        } else {
+            None
+        };
+        self.packets.push_back(packet);
+        evicted
+    }
+
+    pub(crate) fn set_spool(&mut self, spool: Spool) {
+        self.spool = Some(spool);
+    }
+
+    pub(crate) fn spool_mut(&mut self) -> Option<&mut Spool> {
+        self.spool.as_mut()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.packets.is_empty() && self.spool.as_ref().is_none_or(|s| s.is_empty())
+    }
+
+    /// Returns the number of the spans in the in-memory buffer.
+    pub(crate) fn buffered_spans(&self) -> usize {
+        self.packets.iter().map(|p| p.spans).sum()
+    }
+
+    pub(crate) fn front(&self) -> Option<&EncodedPacket> {
+        self.packets.front()
+    }
+
+    pub(crate) fn pop_front(&mut self) -> Option<EncodedPacket> {
+        self.packets.pop_front()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.packets.len()
+    }
+}
+
+/// Encoded packet and the number of the spans contained in it.
+#[derive(Debug)]
+pub(crate) struct EncodedPacket {
+    pub bytes: Vec<u8>,
+    pub spans: usize,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_works() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(300));
+        for _ in 0..10 {
+            let backoff = policy.backoff(0);
+            assert!(backoff >= Duration::from_millis(50));
+            assert!(backoff <= Duration::from_millis(100));
+
+            let backoff = policy.backoff(1);
+            assert!(backoff >= Duration::from_millis(100));
+            assert!(backoff <= Duration::from_millis(200));
+
+            let backoff = policy.backoff(100);
+            assert!(backoff >= Duration::from_millis(150));
+            assert!(backoff <= Duration::from_millis(300));
+        }
+    }
+}
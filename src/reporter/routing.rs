@@ -0,0 +1,365 @@
+use super::fanout::merge_errors;
+use super::Reporter;
+use crate::span::FinishedSpan;
+use crate::{Error, Result};
+use rustracing::tag::TagValue;
+use std::fmt;
+
+/// Condition which selects the spans dispatched to a route of `RoutingReporter`.
+pub struct Route(Condition);
+impl Route {
+    /// Makes a new `Route` which matches spans that have the tag `key` whose value is `value`.
+    pub fn tag<K, V>(key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<TagValue>,
+    {
+        Route(Condition::Tag(key.into(), value.into()))
+    }
+
+    /// Makes a new `Route` which matches spans whose operation name is `name`.
+    pub fn operation_name<N: Into<String>>(name: N) -> Self {
+        Route(Condition::OperationName(name.into()))
+    }
+
+    /// Makes a new `Route` which matches spans that have the baggage item `name` whose value is `value`.
+    ///
+    /// Note that baggage items are propagated to descendant spans (including remote ones),
+    /// so this is suitable to route all the spans of a request (e.g., by a tenant id).
+    pub fn baggage_item<N, V>(name: N, value: V) -> Self
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        Route(Condition::BaggageItem(name.into(), value.into()))
+    }
+
+    /// Makes a new `Route` which matches spans for which `f` returns `true`.
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(&FinishedSpan) -> bool + Send + Sync + 'static,
+    {
+        Route(Condition::Custom(Box::new(f)))
+    }
+
+    /// Returns `true` if `span` matches this route.
+    pub fn is_match(&self, span: &FinishedSpan) -> bool {
+        match &self.0 {
+            Condition::Tag(key, value) => span
+                .tags()
+                .iter()
+                .any(|tag| tag.name() == key && tag.value() == value),
+            Condition::OperationName(name) => span.operation_name() == name,
+            Condition::BaggageItem(name, value) => span
+                .context()
+                .baggage_items()
+                .iter()
+                .any(|item| item.name() == name && item.value() == value),
+            Condition::Custom(f) => f(span),
+        }
+    }
+}
+impl fmt::Debug for Route {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.0 {
+            Condition::Tag(key, value) => write!(f, "Route::tag({:?}, {:?})", key, value),
+            Condition::OperationName(name) => write!(f, "Route::operation_name({:?})", name),
+            Condition::BaggageItem(name, value) => {
+                write!(f, "Route::baggage_item({:?}, {:?})", name, value)
+            }
+            Condition::Custom(_) => write!(f, "Route::custom(_)"),
+        }
+    }
+}
+
+enum Condition {
+    Tag(String, TagValue),
+    OperationName(String),
+    BaggageItem(String, String),
+    Custom(Box<dyn Fn(&FinishedSpan) -> bool + Send + Sync>),
+}
+
+/// Reporter which dispatches each span to one of several reporters by rules.
+///
+/// A span is dispatched to the reporter of the first route it matches,
+/// or to the default reporter if it matches no routes.
+///
+/// Since `FinishedSpan` can not be cloned, `Reporter::report` does not regroup the given spans:
+/// each run of consecutive spans which go to the same route is reported as a batch.
+/// So if the spans of different routes are interleaved, a slice of N spans may result in
+/// up to N reports (e.g., N packets or HTTP requests).
+/// Use `RoutingReporter::report_owned` if the spans can be given by value,
+/// which groups them by route and reports each group at once.
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing::tag::Tag;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::{
+///     ConsoleReporter, JaegerCompactReporter, Reporter, Route, RoutingReporter,
+/// };
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").tag(Tag::new("tenant", "acme")).start();
+/// }
+/// let spans = span_rx.try_iter().collect::<Vec<_>>();
+///
+/// let mut reporter = RoutingReporter::new(JaegerCompactReporter::new("sample_service").unwrap());
+/// reporter.add_route(Route::tag("tenant", "acme"), ConsoleReporter::with_writer(Vec::new()));
+/// reporter.report(&spans).unwrap();
+/// ```
+pub struct RoutingReporter {
+    routes: Vec<(Route, Box<dyn Reporter + Send + Sync>)>,
+    default: Box<dyn Reporter + Send + Sync>,
+}
+impl RoutingReporter {
+    /// Makes a new `RoutingReporter` instance which dispatches all spans to `default`
+    /// until routes are added.
+    pub fn new<R>(default: R) -> Self
+    where
+        R: Reporter + Send + Sync + 'static,
+    {
+        RoutingReporter {
+            routes: Vec::new(),
+            default: Box::new(default),
+        }
+    }
+
+    /// Adds a route which dispatches the spans matching `route` to `reporter`.
+    ///
+    /// Routes are evaluated in the order they are added.
+    pub fn add_route<R>(&mut self, route: Route, reporter: R)
+    where
+        R: Reporter + Send + Sync + 'static,
+    {
+        self.routes.push((route, Box::new(reporter)));
+    }
+
+    /// Returns the number of the routes (excluding the default one).
+    pub fn route_count(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Reports `spans` via the reporters of their routes.
+    ///
+    /// Unlike `Reporter::report`, the spans are grouped by route beforehand,
+    /// so each reporter is called at most once per route
+    /// (the order of the spans in each group is preserved).
+    ///
+    /// # Errors
+    ///
+    /// The same as `Reporter::report`.
+    pub fn report_owned(&self, spans: Vec<FinishedSpan>) -> Result<()> {
+        let mut groups = (0..self.routes.len())
+            .map(|_| Vec::new())
+            .collect::<Vec<_>>();
+        let mut default_group = Vec::new();
+        for span in spans {
+            match self.route_index(&span) {
+                Some(i) => groups[i].push(span),
+                None => default_group.push(span),
+            }
+        }
+
+        let mut batches = 0;
+        let mut errors = Vec::new();
+        let groups = groups
+            .iter()
+            .enumerate()
+            .map(|(i, group)| (Some(i), group))
+            .chain(Some((None, &default_group)));
+        for (index, group) in groups.filter(|(_, group)| !group.is_empty()) {
+            batches += 1;
+            self.report_batch(index, group, &mut errors);
+        }
+        track!(merge_errors(errors, batches, "batches"))
+    }
+
+    /// Returns the index of the route for `span`, or `None` for the default route.
+    fn route_index(&self, span: &FinishedSpan) -> Option<usize> {
+        self.routes.iter().position(|(r, _)| r.is_match(span))
+    }
+
+    /// Reports `batch` via the reporter of the route `index`, and records the error if it fails.
+    fn report_batch(
+        &self,
+        index: Option<usize>,
+        batch: &[FinishedSpan],
+        errors: &mut Vec<(String, Error)>,
+    ) {
+        let (label, reporter) = match index {
+            Some(i) => (format!("[route {}]", i), &self.routes[i].1),
+            None => ("[default]".to_owned(), &self.default),
+        };
+        if let Err(e) = reporter.report(batch) {
+            errors.push((label, e));
+        }
+    }
+}
+impl Reporter for RoutingReporter {
+    /// Reports each of `spans` via the reporter of its route.
+    ///
+    /// Each run of consecutive spans which go to the same route is reported as a batch
+    /// (see the documentation of `RoutingReporter`).
+    ///
+    /// # Errors
+    ///
+    /// All the batches are reported even if some of them fail.
+    ///
+    /// If only one of the batches fails, its error is returned as it is.
+    ///
+    /// If two or more batches fail,
+    /// this method will return an error which has the kind `ErrorKind::Other`
+    /// and describes all the errors.
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        let mut batches = 0;
+        let mut errors = Vec::new();
+        let mut rest = spans;
+        while let Some(first) = rest.first() {
+            let index = self.route_index(first);
+            let len = rest
+                .iter()
+                .position(|span| self.route_index(span) != index)
+                .unwrap_or(rest.len());
+            let (batch, remaining) = rest.split_at(len);
+            rest = remaining;
+            batches += 1;
+            self.report_batch(index, batch, &mut errors);
+        }
+        track!(merge_errors(errors, batches, "batches"))
+    }
+}
+impl fmt::Debug for RoutingReporter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RoutingReporter")
+            .field(
+                "routes",
+                &self.routes.iter().map(|(r, _)| r).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::{SpanContextStateBuilder, TraceId};
+    use crate::{ErrorKind, Tracer};
+    use rustracing::sampler::AllSampler;
+    use rustracing::span::BaggageItem;
+    use rustracing::tag::Tag;
+    use std::sync::{Arc, Mutex};
+    use trackable::result::TestResult;
+
+    #[derive(Debug, Default)]
+    struct RecordingReporter {
+        fail: bool,
+        batches: Mutex<Vec<Vec<String>>>,
+    }
+    impl Reporter for RecordingReporter {
+        fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+            let names = spans
+                .iter()
+                .map(|s| s.operation_name().to_owned())
+                .collect();
+            self.batches.lock().unwrap().push(names);
+            if self.fail {
+                track_panic!(ErrorKind::Other, "down");
+            }
+            Ok(())
+        }
+    }
+    impl RecordingReporter {
+        fn batches(&self) -> Vec<Vec<String>> {
+            self.batches.lock().unwrap().clone()
+        }
+    }
+
+    fn interleaved_spans() -> Vec<FinishedSpan> {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("a").tag(Tag::new("tenant", "acme")).start();
+        tracer.span("b").tag(Tag::new("tenant", "acme")).start();
+        tracer.span("health").start();
+        tracer.span("c").tag(Tag::new("tenant", "other")).start();
+        {
+            let mut span = tracer.span("d").start();
+            span.set_baggage_item(|| BaggageItem::new("tenant", "acme"));
+            let _child = tracer.span("e").child_of(&span).start();
+        }
+        tracer.span("f").tag(Tag::new("tenant", "acme")).start();
+        span_rx.try_iter().collect()
+    }
+
+    fn routing_reporter() -> (
+        RoutingReporter,
+        Arc<RecordingReporter>,
+        Arc<RecordingReporter>,
+        Arc<RecordingReporter>,
+    ) {
+        let acme = Arc::new(RecordingReporter::default());
+        let dropped = Arc::new(RecordingReporter::default());
+        let default = Arc::new(RecordingReporter::default());
+        let mut reporter = RoutingReporter::new(default.clone());
+        reporter.add_route(Route::operation_name("health"), dropped.clone());
+        reporter.add_route(Route::tag("tenant", "acme"), acme.clone());
+        reporter.add_route(Route::baggage_item("tenant", "acme"), acme.clone());
+        (reporter, acme, dropped, default)
+    }
+
+    #[test]
+    fn routing_reporter_works() -> TestResult {
+        let (reporter, acme, dropped, default) = routing_reporter();
+        assert_eq!(reporter.route_count(), 3);
+
+        track!(reporter.report(&interleaved_spans()))?;
+        assert_eq!(acme.batches(), [vec!["a", "b"], vec!["e", "d"], vec!["f"]]);
+        assert_eq!(dropped.batches(), [vec!["health"]]);
+        assert_eq!(default.batches(), [vec!["c"]]);
+        Ok(())
+    }
+
+    #[test]
+    fn report_owned_groups_spans_by_route() -> TestResult {
+        let (reporter, acme, dropped, default) = routing_reporter();
+        track!(reporter.report_owned(interleaved_spans()))?;
+        assert_eq!(acme.batches(), [vec!["a", "b", "f"], vec!["e", "d"]]);
+        assert_eq!(dropped.batches(), [vec!["health"]]);
+        assert_eq!(default.batches(), [vec!["c"]]);
+        Ok(())
+    }
+
+    #[test]
+    fn custom_route_and_errors_work() {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        for low in [1, 2, 3] {
+            let state = SpanContextStateBuilder::new()
+                .trace_id(TraceId { high: 0, low })
+                .finish();
+            tracer.span("foo").start_with_state(state);
+        }
+        let spans = span_rx.try_iter().collect::<Vec<_>>();
+
+        let odd = Arc::new(RecordingReporter {
+            fail: true,
+            ..Default::default()
+        });
+        let default = Arc::new(RecordingReporter {
+            fail: true,
+            ..Default::default()
+        });
+        let mut reporter = RoutingReporter::new(default.clone());
+        reporter.add_route(
+            Route::custom(|span| span.context().state().trace_id().low % 2 == 1),
+            odd.clone(),
+        );
+
+        let e = reporter.report(&spans).err().unwrap();
+        assert_eq!(*e.kind(), ErrorKind::Other);
+        assert!(e.to_string().contains("3 of 3 batches failed: [route 0] "));
+        assert_eq!(odd.batches().len(), 2);
+        assert_eq!(default.batches().len(), 1);
+    }
+}
@@ -0,0 +1,426 @@
+use crate::error;
+use crate::{ErrorKind, Result};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const SEGMENT_FILE_EXTENSION: &str = "spool";
+
+/// The name of the file which records how far the oldest segment has been replayed.
+const OFFSET_FILE_NAME: &str = "replay.offset";
+
+/// The size of a record header (payload length, span count and checksum).
+const RECORD_HEADER_SIZE: u64 = 12;
+
+/// On-disk spool of encoded packets which could not be delivered to the agent.
+///
+/// Packets are appended to segment files in a directory.
+/// When the current segment exceeds the segment size, a new segment is started, and
+/// when the total size exceeds the maximum size, the oldest segments are deleted.
+///
+/// Each record has a checksum, so records truncated by a crash in the middle of a write are
+/// detected and skipped when replaying.
+///
+/// The packets left in the directory by a previous process are replayed as well.
+/// The replay position is persisted, so packets already replayed are not replayed again
+/// after the spool is reopened.
+///
+/// # Examples
+///
+/// ```no_run
+/// use rustracing_jaeger::reporter::{JaegerCompactReporter, Spool};
+///
+/// let mut spool = Spool::open("/var/spool/jaeger").unwrap();
+/// spool.set_max_size(64 * 1024 * 1024);
+///
+/// let mut reporter = JaegerCompactReporter::new("sample_service").unwrap();
+/// reporter.set_spool(spool);
+/// ```
+#[derive(Debug)]
+pub struct Spool {
+    dir: PathBuf,
+    max_size: u64,
+    segment_size: u64,
+    segments: VecDeque<Segment>,
+    next_segment_id: u64,
+    writer: Option<File>,
+    reader: Option<File>,
+    read_offset: u64,
+}
+impl Spool {
+    /// Opens the spool stored in the directory `dir`.
+    ///
+    /// The directory is created if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to create or read the directory,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        track!(fs::create_dir_all(&dir).map_err(error::from_io_error))?;
+
+        let mut segments = Vec::new();
+        for entry in track!(fs::read_dir(&dir).map_err(error::from_io_error))? {
+            let entry = track!(entry.map_err(error::from_io_error))?;
+            let path = entry.path();
+            if path.extension().and_then(|x| x.to_str()) != Some(SEGMENT_FILE_EXTENSION) {
+                continue;
+            }
+            let id = path
+                .file_stem()
+                .and_then(|x| x.to_str())
+                .and_then(|x| x.parse::<u64>().ok());
+            if let Some(id) = id {
+                let size = track!(entry.metadata().map_err(error::from_io_error))?.len();
+                segments.push(Segment { id, size });
+            }
+        }
+        segments.sort_by_key(|s| s.id);
+
+        let next_segment_id = segments.last().map_or(0, |s| s.id + 1);
+        let read_offset = match (segments.first(), read_offset_file(&dir)) {
+            (Some(first), Some((id, offset))) if first.id == id => offset,
+            _ => 0,
+        };
+        Ok(Spool {
+            dir,
+            max_size: 16 * 1024 * 1024,
+            segment_size: 1024 * 1024,
+            segments: segments.into(),
+            next_segment_id,
+            writer: None,
+            reader: None,
+            read_offset,
+        })
+    }
+
+    /// Sets the maximum total size of the segment files in bytes.
+    ///
+    /// The default value is `16 MiB`.
+    pub fn set_max_size(&mut self, size: u64) {
+        self.max_size = size;
+    }
+
+    /// Sets the size in bytes at which a new segment file is started.
+    ///
+    /// The default value is `1 MiB`.
+    pub fn set_segment_size(&mut self, size: u64) {
+        self.segment_size = size;
+    }
+
+    /// Returns `true` if there are no packets waiting to be replayed.
+    pub fn is_empty(&self) -> bool {
+        match self.segments.len() {
+            0 => true,
+            1 => self.read_offset >= self.segments[0].size,
+            _ => false,
+        }
+    }
+
+    /// Appends a packet containing `spans` spans.
+    ///
+    /// Returns the number of the spans dropped to keep the spool within the maximum size.
+    pub(crate) fn append(&mut self, packet: &[u8], spans: usize) -> Result<usize> {
+        let record_size = RECORD_HEADER_SIZE + packet.len() as u64;
+        if record_size > self.max_size {
+            return Ok(spans);
+        }
+
+        let mut dropped = 0;
+        while !self.segments.is_empty() && self.total_size() + record_size > self.max_size {
+            dropped += track!(self.remove_oldest_segment())?;
+        }
+
+        let rotate = match (&self.writer, self.segments.back()) {
+            (Some(_), Some(last)) => last.size > 0 && last.size + record_size > self.segment_size,
+            _ => true,
+        };
+        if rotate {
+            track!(self.start_segment())?;
+        }
+
+        let mut record = Vec::with_capacity(record_size as usize);
+        record.extend_from_slice(&(packet.len() as u32).to_be_bytes());
+        record.extend_from_slice(&(spans as u32).to_be_bytes());
+        record.extend_from_slice(&crc32(packet).to_be_bytes());
+        record.extend_from_slice(packet);
+
+        let writer = track_assert_some!(self.writer.as_mut(), ErrorKind::Other);
+        track!(writer.write_all(&record).map_err(error::from_io_error))?;
+        track!(writer.flush().map_err(error::from_io_error))?;
+        if let Some(last) = self.segments.back_mut() {
+            last.size += record_size;
+        }
+        Ok(dropped)
+    }
+
+    /// Returns the oldest packet which has not been replayed yet.
+    pub(crate) fn peek(&mut self) -> Result<Option<SpooledPacket>> {
+        loop {
+            let segment = match self.segments.front() {
+                None => return Ok(None),
+                Some(segment) => *segment,
+            };
+            let is_writing = self.writer.is_some() && self.segments.len() == 1;
+            if let Some(packet) = track!(self.read_record(segment))? {
+                return Ok(Some(packet));
+            }
+            if is_writing {
+                return Ok(None);
+            }
+
+            // The segment has been consumed, or the rest of it is truncated.
+            track!(self.remove_oldest_segment())?;
+        }
+    }
+
+    /// Marks `packet` as replayed.
+    ///
+    /// The replay position is written to the offset file so that it survives restarts.
+    pub(crate) fn consume(&mut self, packet: &SpooledPacket) -> Result<()> {
+        self.read_offset = packet.next_offset;
+
+        let mut bytes = Vec::with_capacity(20);
+        bytes.extend_from_slice(&packet.segment_id.to_be_bytes());
+        bytes.extend_from_slice(&packet.next_offset.to_be_bytes());
+        bytes.extend_from_slice(&crc32(&bytes).to_be_bytes());
+
+        // Writes a temporary file and renames it so that the offset file is never torn.
+        let path = self.dir.join(OFFSET_FILE_NAME);
+        let tmp_path = path.with_extension("tmp");
+        track!(fs::write(&tmp_path, &bytes).map_err(error::from_io_error))?;
+        track!(fs::rename(&tmp_path, &path).map_err(error::from_io_error))?;
+        Ok(())
+    }
+
+    fn read_record(&mut self, segment: Segment) -> Result<Option<SpooledPacket>> {
+        if self.read_offset + RECORD_HEADER_SIZE > segment.size {
+            return Ok(None);
+        }
+        if self.reader.is_none() {
+            let file =
+                track!(File::open(self.segment_path(segment.id)).map_err(error::from_io_error))?;
+            self.reader = Some(file);
+        }
+        let reader = track_assert_some!(self.reader.as_mut(), ErrorKind::Other);
+        track!(reader
+            .seek(SeekFrom::Start(self.read_offset))
+            .map_err(error::from_io_error))?;
+
+        let mut header = [0; RECORD_HEADER_SIZE as usize];
+        if !track!(read_exact_or_eof(reader, &mut header))? {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let spans = u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let checksum = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+        let next_offset = self.read_offset + RECORD_HEADER_SIZE + len;
+        if next_offset > segment.size {
+            return Ok(None);
+        }
+
+        let mut bytes = vec![0; len as usize];
+        if !track!(read_exact_or_eof(reader, &mut bytes))? || crc32(&bytes) != checksum {
+            return Ok(None);
+        }
+        Ok(Some(SpooledPacket {
+            bytes,
+            spans,
+            segment_id: segment.id,
+            next_offset,
+        }))
+    }
+
+    fn start_segment(&mut self) -> Result<()> {
+        let id = self.next_segment_id;
+        let file = track!(OpenOptions::new()
+            .create_new(true)
+            .append(true)
+            .open(self.segment_path(id))
+            .map_err(error::from_io_error))?;
+        self.next_segment_id += 1;
+        self.writer = Some(file);
+        self.segments.push_back(Segment { id, size: 0 });
+        Ok(())
+    }
+
+    /// Removes the oldest segment and returns the number of the unreplayed spans in it.
+    fn remove_oldest_segment(&mut self) -> Result<usize> {
+        let mut spans = 0;
+        if let Some(segment) = self.segments.front().copied() {
+            while let Some(packet) = track!(self.read_record(segment))? {
+                spans += packet.spans;
+                self.read_offset = packet.next_offset;
+            }
+        }
+
+        let segment = track_assert_some!(self.segments.pop_front(), ErrorKind::Other);
+        if self.segments.is_empty() {
+            self.writer = None;
+        }
+        self.reader = None;
+        self.read_offset = 0;
+        match fs::remove_file(self.segment_path(segment.id)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(track!(error::from_io_error(e)));
+            }
+            _ => {}
+        }
+        Ok(spans)
+    }
+
+    fn total_size(&self) -> u64 {
+        self.segments.iter().map(|s| s.size).sum()
+    }
+
+    fn segment_path(&self, id: u64) -> PathBuf {
+        self.dir
+            .join(format!("{:020}.{}", id, SEGMENT_FILE_EXTENSION))
+    }
+}
+
+/// Packet read from a spool.
+#[derive(Debug)]
+pub(crate) struct SpooledPacket {
+    pub bytes: Vec<u8>,
+    pub spans: usize,
+    segment_id: u64,
+    next_offset: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    id: u64,
+    size: u64,
+}
+
+/// Reads the offset file in `dir`, returning the segment id and the replay offset in it.
+///
+/// `None` is returned if the file does not exist or is broken.
+fn read_offset_file(dir: &Path) -> Option<(u64, u64)> {
+    let bytes = fs::read(dir.join(OFFSET_FILE_NAME)).ok()?;
+    if bytes.len() != 20 {
+        return None;
+    }
+    let (body, checksum) = bytes.split_at(16);
+    if crc32(body).to_be_bytes() != checksum {
+        return None;
+    }
+    let mut id = [0; 8];
+    let mut offset = [0; 8];
+    id.copy_from_slice(&body[..8]);
+    offset.copy_from_slice(&body[8..]);
+    Some((u64::from_be_bytes(id), u64::from_be_bytes(offset)))
+}
+
+/// Fills `buf`, returning `false` if the end of the file is reached before that.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(track!(error::from_io_error(e))),
+    }
+}
+
+/// CRC-32 (IEEE 802.3).
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            let mask = (!(crc & 1)).wrapping_add(1);
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reporter::temp_dir;
+    use trackable::result::TestResult;
+
+    fn replay(spool: &mut Spool) -> Result<Vec<Vec<u8>>> {
+        let mut packets = Vec::new();
+        while let Some(packet) = track!(spool.peek())? {
+            track!(spool.consume(&packet))?;
+            packets.push(packet.bytes);
+        }
+        Ok(packets)
+    }
+
+    #[test]
+    fn crc32_works() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn spool_works() -> TestResult {
+        let dir = temp_dir("spool_works");
+        {
+            let mut spool = track!(Spool::open(&dir))?;
+            spool.set_segment_size(30);
+            assert!(spool.is_empty());
+            for i in 0..5u8 {
+                track!(spool.append(&[i; 10], 1))?;
+            }
+            assert!(!spool.is_empty());
+
+            let packet = track_assert_some!(track!(spool.peek())?, trackable::error::Failed);
+            assert_eq!(packet.bytes, vec![0; 10]);
+            track!(spool.consume(&packet))?;
+        }
+
+        // Truncates the last record as if the process crashed in the middle of a write.
+        let last_segment = track_any_err!(fs::read_dir(&dir))?
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().and_then(|x| x.to_str()) == Some(SEGMENT_FILE_EXTENSION))
+            .max()
+            .unwrap();
+        let size = track_any_err!(fs::metadata(&last_segment))?.len();
+        let file = track_any_err!(OpenOptions::new().write(true).open(&last_segment))?;
+        track_any_err!(file.set_len(size - 3))?;
+
+        // Reopens (the first record has already been replayed, so it is not replayed again).
+        let mut spool = track!(Spool::open(&dir))?;
+        let packets = track!(replay(&mut spool))?;
+        assert_eq!(packets, vec![vec![1; 10], vec![2; 10], vec![3; 10]]);
+        assert!(spool.is_empty());
+
+        // Reopens again after all the records have been replayed.
+        track!(spool.append(&[5; 10], 1))?;
+        let packet = track_assert_some!(track!(spool.peek())?, trackable::error::Failed);
+        track!(spool.consume(&packet))?;
+        track!(spool.append(&[6; 10], 1))?;
+        drop(spool);
+        let mut spool = track!(Spool::open(&dir))?;
+        assert_eq!(track!(replay(&mut spool))?, vec![vec![6; 10]]);
+
+        track_any_err!(fs::remove_dir_all(&dir))?;
+        Ok(())
+    }
+
+    #[test]
+    fn spool_max_size_works() -> TestResult {
+        let dir = temp_dir("spool_max_size_works");
+        let mut spool = track!(Spool::open(&dir))?;
+        spool.set_segment_size(22);
+        spool.set_max_size(66);
+
+        let mut dropped = 0;
+        for i in 0..5u8 {
+            dropped += track!(spool.append(&[i; 10], 2))?;
+        }
+        assert_eq!(dropped, 4);
+        assert_eq!(
+            track!(replay(&mut spool))?,
+            vec![vec![2; 10], vec![3; 10], vec![4; 10]]
+        );
+
+        track_any_err!(fs::remove_dir_all(&dir))?;
+        Ok(())
+    }
+}
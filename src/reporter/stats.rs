@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Snapshot of the statistics of a reporter.
+///
+/// # Examples
+///
+/// ```
+/// use rustracing_jaeger::reporter::JaegerCompactReporter;
+///
+/// let reporter = JaegerCompactReporter::new("sample_service").unwrap();
+/// let stats = reporter.stats();
+/// assert_eq!(stats.spans_sent, 0);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReporterStats {
+    /// The number of the spans sent to the agent.
+    pub spans_sent: u64,
+
+    /// The number of the batches (i.e., packets) sent to the agent.
+    pub batches_sent: u64,
+
+    /// The number of the bytes sent to the agent.
+    pub bytes_written: u64,
+
+    /// The number of the batches which failed to be encoded.
+    pub encode_failures: u64,
+
+    /// The number of the batches which failed to be sent to the agent.
+    ///
+    /// A batch is counted once even if it is retried according to the retry policy.
+    pub send_failures: u64,
+
+    /// The number of the batches evicted from the retry buffer because it was full.
+    pub retry_buffer_overflows: u64,
+
+    /// The number of the spans dropped because the retry buffer (or the spool) was full.
+    pub retry_buffer_dropped_spans: u64,
+}
+
+/// Counters from which `ReporterStats` is made.
+#[derive(Debug, Default)]
+pub(crate) struct ReporterCounters {
+    spans_sent: AtomicU64,
+    batches_sent: AtomicU64,
+    bytes_written: AtomicU64,
+    encode_failures: AtomicU64,
+    send_failures: AtomicU64,
+    retry_buffer_overflows: AtomicU64,
+    retry_buffer_dropped_spans: AtomicU64,
+}
+impl ReporterCounters {
+    pub(crate) fn record_sent(&self, bytes: usize, spans: usize) {
+        self.spans_sent.fetch_add(spans as u64, Ordering::Relaxed);
+        self.batches_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_encode_failure(&self) {
+        self.encode_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_send_failure(&self) {
+        self.send_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_retry_buffer_overflow(&self, batches: usize, spans: usize) {
+        self.retry_buffer_overflows
+            .fetch_add(batches as u64, Ordering::Relaxed);
+        self.retry_buffer_dropped_spans
+            .fetch_add(spans as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> ReporterStats {
+        ReporterStats {
+            spans_sent: self.spans_sent.load(Ordering::Relaxed),
+            batches_sent: self.batches_sent.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            encode_failures: self.encode_failures.load(Ordering::Relaxed),
+            send_failures: self.send_failures.load(Ordering::Relaxed),
+            retry_buffer_overflows: self.retry_buffer_overflows.load(Ordering::Relaxed),
+            retry_buffer_dropped_spans: self.retry_buffer_dropped_spans.load(Ordering::Relaxed),
+        }
+    }
+}
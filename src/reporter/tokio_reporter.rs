@@ -0,0 +1,446 @@
+use super::remote::{Intake, IntakeCommand, Signal, SignalSender, SpanQueue};
+use super::{
+    check_too_large_spans, BatchFormat, JaegerReporter, PriorityPolicy, Protocol,
+    DEFAULT_MAX_PACKET_SIZE,
+};
+use crate::error;
+use crate::span::SpanReceiver;
+use crate::thrift::jaeger;
+use crate::Result;
+use crossbeam_channel::{self as channel, Sender};
+use rustracing::tag::Tag;
+use std::net::SocketAddr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+
+/// `AsyncReporter` builder.
+#[derive(Debug)]
+pub struct AsyncReporterBuilder {
+    service_name: String,
+    protocol: Protocol,
+    span_rx: SpanReceiver,
+    agent_addr: SocketAddr,
+    reporter_addr: SocketAddr,
+    service_tags: Vec<Tag>,
+    max_packet_size: usize,
+    queue_size: usize,
+    priority_policy: PriorityPolicy,
+    max_batch_size: usize,
+    flush_interval: Duration,
+}
+impl AsyncReporterBuilder {
+    /// Makes a new `AsyncReporterBuilder` which reports the spans received from `span_rx`
+    /// to the agent which accepts jaeger.thrift over compact thrift protocol.
+    pub fn compact(service_name: &str, span_rx: SpanReceiver) -> Self {
+        Self::new(service_name, Protocol::Compact, span_rx)
+    }
+
+    /// Makes a new `AsyncReporterBuilder` which reports the spans received from `span_rx`
+    /// to the agent which accepts jaeger.thrift over binary thrift protocol.
+    pub fn binary(service_name: &str, span_rx: SpanReceiver) -> Self {
+        Self::new(service_name, Protocol::Binary, span_rx)
+    }
+
+    fn new(service_name: &str, protocol: Protocol, span_rx: SpanReceiver) -> Self {
+        AsyncReporterBuilder {
+            service_name: service_name.to_owned(),
+            protocol,
+            span_rx,
+            agent_addr: SocketAddr::from(([127, 0, 0, 1], protocol.default_port())),
+            reporter_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            service_tags: Vec::new(),
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            queue_size: 1000,
+            priority_policy: PriorityPolicy::DebugFirst,
+            max_batch_size: 100,
+            flush_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the address of the report destination agent.
+    ///
+    /// The default address is `127.0.0.1:6831` for compact and `127.0.0.1:6832` for binary.
+    pub fn agent_addr(mut self, addr: SocketAddr) -> Self {
+        self.agent_addr = addr;
+        self
+    }
+
+    /// Sets the address to which the reporter bind.
+    ///
+    /// The default address is `127.0.0.1:0`.
+    pub fn reporter_addr(mut self, addr: SocketAddr) -> Self {
+        self.reporter_addr = addr;
+        self
+    }
+
+    /// Adds `tag` to this service.
+    pub fn service_tag(mut self, tag: Tag) -> Self {
+        self.service_tags.push(tag);
+        self
+    }
+
+    /// Sets the maximum size of a packet sent to the agent.
+    ///
+    /// The default value is `65000`.
+    pub fn max_packet_size(mut self, size: usize) -> Self {
+        self.max_packet_size = size;
+        self
+    }
+
+    /// Sets the maximum number of spans waiting to be reported.
+    ///
+    /// If the queue is full, newly received spans are discarded (see `priority_policy` for the exception).
+    /// The number of the discarded spans is reported to the agent as `fullQueueDroppedSpans`.
+    ///
+    /// The default value is `1000`.
+    pub fn queue_size(mut self, size: usize) -> Self {
+        self.queue_size = size;
+        self
+    }
+
+    /// Sets the policy for prioritizing queued spans.
+    ///
+    /// The default value is `PriorityPolicy::DebugFirst`.
+    pub fn priority_policy(mut self, policy: PriorityPolicy) -> Self {
+        self.priority_policy = policy;
+        self
+    }
+
+    /// Sets the maximum number of spans included in a batch.
+    ///
+    /// When the number of queued spans reaches this value, they are reported immediately
+    /// without waiting for the flush interval.
+    ///
+    /// The default value is `100`.
+    pub fn max_batch_size(mut self, size: usize) -> Self {
+        self.max_batch_size = size;
+        self
+    }
+
+    /// Sets the interval at which queued spans are reported.
+    ///
+    /// The default value is `Duration::from_secs(1)`.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Binds the UDP socket, spawns the reporting task and returns an `AsyncReporter` handle.
+    ///
+    /// This method must be called within a tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// If the UDP socket can not be bound to the reporter address or connected to the agent address,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub async fn finish(self) -> Result<AsyncReporter> {
+        let socket = track!(UdpSocket::bind(self.reporter_addr)
+            .await
+            .map_err(error::from_io_error))?;
+        track!(socket
+            .connect(self.agent_addr)
+            .await
+            .map_err(error::from_io_error))?;
+        let mut reporter = JaegerReporter::new(
+            &self.service_name,
+            BatchFormat::Agent(self.protocol),
+            socket,
+        );
+        reporter.set_max_packet_size(self.max_packet_size);
+        for tag in self.service_tags {
+            reporter.add_service_tag(tag);
+        }
+
+        let max_batch_size = std::cmp::max(1, self.max_batch_size);
+        let queue = Arc::new(SpanQueue::new(
+            self.queue_size,
+            self.priority_policy,
+            Arc::clone(&reporter.full_queue_dropped_spans),
+        ));
+        let (command_tx, command_rx) = channel::unbounded();
+        let (signal_tx, signal_rx) = mpsc::unbounded_channel();
+        let intake = Intake::new(
+            self.span_rx,
+            command_rx,
+            signal_tx,
+            Arc::clone(&queue),
+            max_batch_size,
+        );
+        thread::spawn(move || intake.run());
+
+        let worker = Worker {
+            reporter,
+            signal_rx,
+            queue,
+            max_batch_size,
+            flush_interval: self.flush_interval,
+        };
+        Ok(AsyncReporter {
+            command_tx,
+            task: Some(tokio::spawn(worker.run())),
+        })
+    }
+}
+
+/// Asynchronous reporter which reports spans received from a `SpanReceiver` using tokio.
+///
+/// Received spans are queued in the same way as `RemoteReporter` (including `PriorityPolicy`),
+/// and sent via `tokio::net::UdpSocket` in batches,
+/// either when the number of queued spans reaches the maximum batch size
+/// or when the flush interval elapses.
+///
+/// Unlike `RemoteReporter`, failed packets are not retried nor buffered
+/// (i.e., `RetryPolicy` and the retry buffer are not supported).
+/// The spans of such packets are counted as `failedToEmitSpans`.
+///
+/// Since `SpanReceiver` is a blocking channel, a dedicated thread moves received spans to the queue
+/// (the same as `RemoteReporter`), while the batching and sending are done by a tokio task.
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::AsyncReporterBuilder;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (span_tx, span_rx) = crossbeam_channel::bounded(10);
+/// let tracer = Tracer::with_sender(AllSampler, span_tx);
+///
+/// let reporter = AsyncReporterBuilder::compact("sample_service", span_rx)
+///     .finish()
+///     .await
+///     .unwrap();
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// reporter.flush().await.unwrap();
+/// reporter.shutdown().await.unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct AsyncReporter {
+    command_tx: Sender<Command>,
+    task: Option<JoinHandle<()>>,
+}
+impl AsyncReporter {
+    /// Reports all the spans received so far and waits for the completion.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to report some of the spans,
+    /// this method will return the last error occurred during the flush.
+    pub async fn flush(&self) -> Result<()> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if self.command_tx.send(Command::Flush(reply_tx)).is_err() {
+            // The reporter has already finished.
+            return Ok(());
+        }
+        match reply_rx.await {
+            Err(_) => Ok(()),
+            Ok(result) => track!(result),
+        }
+    }
+
+    /// Reports the remaining spans and stops the reporting task.
+    ///
+    /// Note that spans sent to the `SpanReceiver` after this method is called are not reported.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to report some of the remaining spans,
+    /// this method will return the last error occurred.
+    pub async fn shutdown(mut self) -> Result<()> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let _ = self.command_tx.send(Command::Shutdown(Some(reply_tx)));
+        let result = reply_rx.await.unwrap_or(Ok(()));
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+        track!(result)
+    }
+}
+impl Drop for AsyncReporter {
+    fn drop(&mut self) {
+        if self.task.is_some() {
+            // The remaining spans are reported in the background.
+            let _ = self.command_tx.send(Command::Shutdown(None));
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Command {
+    Flush(oneshot::Sender<Result<()>>),
+    Shutdown(Option<oneshot::Sender<Result<()>>>),
+}
+
+impl IntakeCommand for Command {
+    fn stop() -> Self {
+        Command::Shutdown(None)
+    }
+
+    fn is_stop(&self) -> bool {
+        matches!(self, Command::Shutdown(_))
+    }
+}
+
+impl SignalSender<Command> for mpsc::UnboundedSender<Signal<Command>> {
+    fn send_signal(&self, signal: Signal<Command>) -> bool {
+        self.send(signal).is_ok()
+    }
+}
+
+/// Reports queued spans.
+#[derive(Debug)]
+struct Worker {
+    reporter: JaegerReporter<UdpSocket>,
+    signal_rx: mpsc::UnboundedReceiver<Signal<Command>>,
+    queue: Arc<SpanQueue>,
+    max_batch_size: usize,
+    flush_interval: Duration,
+}
+impl Worker {
+    async fn run(mut self) {
+        let mut interval = time::interval(self.flush_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                signal = self.signal_rx.recv() => match signal {
+                    Some(Signal::BatchReady) => {
+                        while self.queue.len() >= self.max_batch_size {
+                            let batch = self.queue.pop_batch(self.max_batch_size);
+                            let _ = self.reporter.report_spans_async(batch).await;
+                        }
+                    }
+                    Some(Signal::Command(Command::Flush(reply_tx))) => {
+                        let _ = reply_tx.send(self.flush().await);
+                    }
+                    Some(Signal::Command(Command::Shutdown(reply_tx))) => {
+                        let result = self.flush().await;
+                        if let Some(reply_tx) = reply_tx {
+                            let _ = reply_tx.send(result);
+                        }
+                        return;
+                    }
+                    None => {
+                        let _ = self.flush().await;
+                        return;
+                    }
+                },
+                _ = interval.tick() => {
+                    let _ = self.flush().await;
+                }
+            }
+        }
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let mut result = Ok(());
+        loop {
+            let batch = self.queue.pop_batch(self.max_batch_size);
+            if batch.is_empty() {
+                return result;
+            }
+            if let Err(e) = track!(self.reporter.report_spans_async(batch).await) {
+                result = Err(e);
+            }
+        }
+    }
+}
+
+impl JaegerReporter<UdpSocket> {
+    async fn report_spans_async(&self, spans: Vec<jaeger::Span>) -> Result<()> {
+        let (packets, dropped) = track!(self.prepare_packets(spans))?;
+        let mut result = Ok(());
+        for packet in packets {
+            let sent = self.transport.send(&packet.bytes).await;
+            self.record_send_result(&packet.bytes, packet.spans, sent.is_ok());
+            if let Err(e) = sent {
+                // Failed packets are not buffered (see the documentation of `AsyncReporter`).
+                self.failed_to_emit_spans
+                    .fetch_add(packet.spans as u64, Ordering::Relaxed);
+                result = Err(track!(error::from_io_error(e)));
+            }
+        }
+        track!(check_too_large_spans(dropped, self.max_packet_size))?;
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::span::SpanContextStateBuilder;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use trackable::result::TestResult;
+
+    #[tokio::test]
+    async fn async_reporter_works() -> TestResult {
+        let agent = track_any_err!(std::net::UdpSocket::bind("127.0.0.1:0"))?;
+        track_any_err!(agent.set_read_timeout(Some(Duration::from_secs(5))))?;
+
+        let (span_tx, span_rx) = crossbeam_channel::bounded(10);
+        let tracer = Tracer::with_sender(AllSampler, span_tx);
+        let reporter = track!(
+            AsyncReporterBuilder::binary("async_reporter_test", span_rx)
+                .agent_addr(track_any_err!(agent.local_addr())?)
+                .flush_interval(Duration::from_secs(60))
+                .finish()
+                .await
+        )?;
+
+        // Reported by an explicit flush
+        tracer.span("foo").start();
+        track!(reporter.flush().await)?;
+        let mut buf = [0; 65536];
+        track_any_err!(agent.recv(&mut buf))?;
+
+        // Reported by the shutdown
+        tracer.span("bar").start();
+        track!(reporter.shutdown().await)?;
+        track_any_err!(agent.recv(&mut buf))?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn debug_spans_are_prioritized() -> TestResult {
+        let agent = track_any_err!(std::net::UdpSocket::bind("127.0.0.1:0"))?;
+        track_any_err!(agent.set_read_timeout(Some(Duration::from_secs(5))))?;
+
+        let (span_tx, span_rx) = crossbeam_channel::bounded(10);
+        let tracer = Tracer::with_sender(AllSampler, span_tx);
+        let reporter = track!(
+            AsyncReporterBuilder::compact("async_reporter_test", span_rx)
+                .agent_addr(track_any_err!(agent.local_addr())?)
+                .queue_size(1)
+                .flush_interval(Duration::from_secs(60))
+                .finish()
+                .await
+        )?;
+
+        // The ordinary span is evicted to make room for the debug span.
+        tracer.span("ordinary_span").start();
+        let state = SpanContextStateBuilder::new()
+            .debug_id("foo".to_owned())
+            .finish();
+        tracer.span("debug_span").start_with_state(state);
+        track!(reporter.flush().await)?;
+
+        let mut buf = [0; 65536];
+        let size = track_any_err!(agent.recv(&mut buf))?;
+        let packet = String::from_utf8_lossy(&buf[..size]);
+        assert!(packet.contains("debug_span"));
+        assert!(!packet.contains("ordinary_span"));
+        track!(reporter.shutdown().await)?;
+        Ok(())
+    }
+}
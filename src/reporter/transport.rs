@@ -0,0 +1,412 @@
+use crate::error;
+use crate::{ErrorKind, Result};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The default interval at which the agent host name is re-resolved.
+const DEFAULT_RESOLVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Transport used by reporters to send encoded packets to the agent.
+pub trait Transport {
+    /// Sends `packet` to the agent.
+    fn send(&self, packet: &[u8]) -> Result<()>;
+
+    /// Returns `false` if it is detected that the agent is unreachable.
+    ///
+    /// The default implementation always returns `true`.
+    fn is_agent_healthy(&self) -> bool {
+        true
+    }
+}
+
+/// Transport which sends packets to the agent via UDP.
+#[derive(Debug)]
+pub struct UdpTransport {
+    agent: Mutex<Agent>,
+    resolver: Arc<Resolver>,
+    connected: bool,
+    healthy: AtomicBool,
+    resolve_interval: Duration,
+}
+impl UdpTransport {
+    /// Makes a new `UdpTransport` instance which sends packets to `agent_addr`.
+    ///
+    /// # Errors
+    ///
+    /// If the UDP socket can not be bound to `127.0.0.1:0`,
+    /// it will return an error which has the kind `ErrorKind::Other`.
+    pub fn new(agent_addr: SocketAddr) -> Result<Self> {
+        let socket =
+            track!(UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+                .map_err(error::from_io_error))?;
+        let agent = Agent {
+            socket,
+            addr: agent_addr,
+            host: None,
+            resolved_at: Instant::now(),
+        };
+        Ok(UdpTransport {
+            agent: Mutex::new(agent),
+            resolver: Arc::default(),
+            connected: false,
+            healthy: AtomicBool::new(true),
+            resolve_interval: DEFAULT_RESOLVE_INTERVAL,
+        })
+    }
+
+    /// Sets the address of the agent to `addr`.
+    pub fn set_agent_addr(&mut self, addr: SocketAddr) {
+        let agent = self.agent_mut();
+        agent.addr = addr;
+        agent.host = None;
+        self.healthy.store(true, Ordering::Relaxed);
+    }
+
+    /// Sets the address of the agent to `host` (e.g., `"jaeger-agent:6831"`).
+    ///
+    /// `host` is resolved immediately and then re-resolved periodically (see `set_resolve_interval`).
+    /// The re-resolution is done by a background thread, so it never blocks `send`.
+    ///
+    /// # Errors
+    ///
+    /// If `host` can not be resolved, this method will return an error.
+    pub fn set_agent_host(&mut self, host: &str) -> Result<()> {
+        let addr = track!(resolve(host))?;
+        let agent = self.agent_mut();
+        track!(agent.update_addr(addr))?;
+        agent.host = Some(host.to_owned());
+        self.healthy.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Sets the interval at which the agent host name given by `set_agent_host` is re-resolved.
+    pub fn set_resolve_interval(&mut self, interval: Duration) {
+        self.resolve_interval = interval;
+    }
+
+    /// Sets the address to which the UDP socket bind.
+    pub fn set_reporter_addr(&mut self, addr: SocketAddr) -> Result<()> {
+        self.agent_mut().socket = track!(UdpSocket::bind(addr).map_err(error::from_io_error))?;
+        Ok(())
+    }
+
+    /// Enables or disables the connected mode.
+    pub fn set_connected_mode(&mut self, enabled: bool) -> Result<()> {
+        if self.connected && !enabled {
+            // There is no portable way to disconnect a UDP socket.
+            let addr = track!(self
+                .agent_mut()
+                .socket
+                .local_addr()
+                .map_err(error::from_io_error))?;
+            track!(self.set_reporter_addr(SocketAddr::new(addr.ip(), 0)))?;
+        }
+        self.connected = enabled;
+        self.healthy.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn agent(&self) -> MutexGuard<'_, Agent> {
+        self.agent.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn agent_mut(&mut self) -> &mut Agent {
+        self.agent.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn local_addr(&self) -> Result<SocketAddr> {
+        track!(self
+            .agent()
+            .socket
+            .local_addr()
+            .map_err(error::from_io_error))
+    }
+}
+impl Transport for UdpTransport {
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        let mut agent = self.agent();
+        if let Some(host) = agent.host.clone() {
+            if let Some(addr) = self.resolver.take_resolved(&host) {
+                // If the socket can not be rebound, the last known address continues to be used.
+                let _ = agent.update_addr(addr);
+            }
+            if agent.resolved_at.elapsed() >= self.resolve_interval {
+                agent.resolved_at = Instant::now();
+                self.resolver.start(host);
+            }
+        }
+        let result = if self.connected {
+            if agent.socket.peer_addr().ok() != Some(agent.addr) {
+                track!(agent
+                    .socket
+                    .connect(agent.addr)
+                    .map_err(error::from_io_error))?;
+            }
+            agent.socket.send(packet)
+        } else {
+            agent.socket.send_to(packet, agent.addr)
+        };
+        track!(update_health(&self.healthy, result))
+    }
+
+    fn is_agent_healthy(&self) -> bool {
+        if self.connected {
+            // Picks up the error caused by an ICMP message received after the last send.
+            if let Ok(Some(e)) = self.agent().socket.take_error() {
+                let _ = update_health(&self.healthy, Err(e));
+            }
+        }
+        self.healthy.load(Ordering::Relaxed)
+    }
+}
+
+/// Transport which sends packets to the agent via a Unix datagram socket.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixDatagramTransport {
+    socket: UnixDatagram,
+    path: Option<PathBuf>,
+    healthy: AtomicBool,
+}
+#[cfg(unix)]
+impl UnixDatagramTransport {
+    /// Makes a new `UnixDatagramTransport` instance which sends packets to the socket bound to `path`.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to create an unbound Unix datagram socket,
+    /// it will return an error which has the kind `ErrorKind::Other`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let socket = track!(UnixDatagram::unbound().map_err(error::from_io_error))?;
+        Ok(UnixDatagramTransport {
+            socket,
+            path: Some(path.as_ref().to_path_buf()),
+            healthy: AtomicBool::new(true),
+        })
+    }
+
+    /// Makes a new `UnixDatagramTransport` instance which sends packets via the connected `socket`.
+    pub fn from_socket(socket: UnixDatagram) -> Self {
+        UnixDatagramTransport {
+            socket,
+            path: None,
+            healthy: AtomicBool::new(true),
+        }
+    }
+}
+#[cfg(unix)]
+impl Transport for UnixDatagramTransport {
+    fn send(&self, packet: &[u8]) -> Result<()> {
+        let result = if let Some(path) = &self.path {
+            self.socket.send_to(packet, path)
+        } else {
+            self.socket.send(packet)
+        };
+        track!(update_health(&self.healthy, result))
+    }
+
+    fn is_agent_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+}
+
+fn update_health(healthy: &AtomicBool, result: io::Result<usize>) -> Result<()> {
+    match result {
+        Ok(_) => {
+            healthy.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+        Err(e) => {
+            if matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionRefused | io::ErrorKind::NotFound
+            ) {
+                healthy.store(false, Ordering::Relaxed);
+            }
+            Err(track!(error::from_io_error(e)))
+        }
+    }
+}
+
+/// The destination agent and the UDP socket used to send packets to it.
+#[derive(Debug)]
+struct Agent {
+    socket: UdpSocket,
+    addr: SocketAddr,
+    host: Option<String>,
+    resolved_at: Instant,
+}
+impl Agent {
+    fn update_addr(&mut self, addr: SocketAddr) -> Result<()> {
+        let local_addr = track!(self.socket.local_addr().map_err(error::from_io_error))?;
+        if local_addr.is_ipv4() != addr.is_ipv4() {
+            // Rebinds the socket to the address of the same family as the agent.
+            let ip = match (addr, local_addr.ip().is_loopback()) {
+                (SocketAddr::V4(_), true) => IpAddr::from(Ipv4Addr::LOCALHOST),
+                (SocketAddr::V4(_), false) => IpAddr::from(Ipv4Addr::UNSPECIFIED),
+                (SocketAddr::V6(_), true) => IpAddr::from(Ipv6Addr::LOCALHOST),
+                (SocketAddr::V6(_), false) => IpAddr::from(Ipv6Addr::UNSPECIFIED),
+            };
+            self.socket =
+                track!(UdpSocket::bind(SocketAddr::new(ip, 0)).map_err(error::from_io_error))?;
+        }
+        self.addr = addr;
+        self.resolved_at = Instant::now();
+        Ok(())
+    }
+}
+
+/// Resolver which re-resolves the agent host name in a background thread.
+#[derive(Debug, Default)]
+struct Resolver {
+    in_flight: AtomicBool,
+
+    /// The host name and the address resolved most recently (which has not been taken yet).
+    resolved: Mutex<Option<(String, SocketAddr)>>,
+}
+impl Resolver {
+    /// Starts resolving `host` unless another resolution is in flight.
+    ///
+    /// If the resolution fails, nothing is stored (i.e., the last known address continues to be used).
+    fn start(self: &Arc<Self>, host: String) {
+        if self.in_flight.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let this = Arc::clone(self);
+        let spawned = thread::Builder::new()
+            .name("rustracing_jaeger_resolver".to_owned())
+            .spawn(move || {
+                if let Ok(addr) = resolve(&host) {
+                    *this.lock_resolved() = Some((host, addr));
+                }
+                this.in_flight.store(false, Ordering::SeqCst);
+            });
+        if spawned.is_err() {
+            self.in_flight.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Takes the address resolved for `host` if exists.
+    ///
+    /// The results for other hosts (i.e., before the agent host is changed) are discarded.
+    fn take_resolved(&self, host: &str) -> Option<SocketAddr> {
+        let (resolved_host, addr) = self.lock_resolved().take()?;
+        if resolved_host == host {
+            Some(addr)
+        } else {
+            None
+        }
+    }
+
+    fn lock_resolved(&self) -> MutexGuard<'_, Option<(String, SocketAddr)>> {
+        self.resolved.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn resolve(host: &str) -> Result<SocketAddr> {
+    let mut addrs = track!(
+        host.to_socket_addrs().map_err(error::from_io_error),
+        "host={:?}",
+        host
+    )?;
+    let addr = track_assert_some!(addrs.next(), ErrorKind::InvalidInput, "host={:?}", host);
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn agent_host_is_reresolved_in_background() -> TestResult {
+        let old_agent = track_any_err!(UdpSocket::bind("127.0.0.1:0"))?;
+        let new_agent = track_any_err!(UdpSocket::bind("127.0.0.1:0"))?;
+        track_any_err!(new_agent.set_read_timeout(Some(Duration::from_millis(100))))?;
+
+        let mut transport = track!(UdpTransport::new(track_any_err!(old_agent.local_addr())?))?;
+        let old_host = format!(
+            "127.0.0.1:{}",
+            track_any_err!(old_agent.local_addr())?.port()
+        );
+        track!(transport.set_agent_host(&old_host))?;
+        transport.set_resolve_interval(Duration::from_millis(0));
+
+        // Emulates the change of the address which the host name is resolved to.
+        let new_host = format!(
+            "127.0.0.1:{}",
+            track_any_err!(new_agent.local_addr())?.port()
+        );
+        transport.agent_mut().host = Some(new_host);
+
+        let mut buf = [0; 16];
+        for _ in 0..50 {
+            track!(transport.send(b"foo"))?;
+            if new_agent.recv(&mut buf).is_ok() {
+                assert_eq!(
+                    transport.agent().addr,
+                    track_any_err!(new_agent.local_addr())?
+                );
+                return Ok(());
+            }
+        }
+        panic!("The agent host was not re-resolved");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_datagram_transport_works() -> TestResult {
+        use crate::reporter::JaegerBinaryReporter;
+        use crate::Tracer;
+        use rustracing::sampler::AllSampler;
+        use rustracing::tag::Tag;
+
+        let (tx, rx) = track_any_err!(UnixDatagram::pair())?;
+        track_any_err!(rx.set_read_timeout(Some(Duration::from_secs(5))))?;
+
+        let transport = UnixDatagramTransport::from_socket(tx);
+        let mut reporter = JaegerBinaryReporter::with_transport("unix_test", transport);
+        reporter.set_max_packet_size(2000);
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        for _ in 0..10 {
+            let _span = tracer
+                .span("unix")
+                .tag(Tag::new("payload", "x".repeat(300)))
+                .start();
+        }
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let mut buf = [0; 65536];
+        let mut total_size = 0;
+        while total_size < 10 * 300 {
+            let size = track_any_err!(rx.recv(&mut buf))?;
+            assert!(size <= 2000);
+            total_size += size;
+        }
+        assert!(reporter.is_agent_healthy());
+
+        drop(rx);
+        {
+            let _span = tracer.span("unix").start();
+        }
+        let e = track_assert_some!(
+            reporter
+                .report(&span_rx.try_iter().collect::<Vec<_>>())
+                .err(),
+            trackable::error::Failed
+        );
+        assert!(crate::reporter::is_agent_unreachable(&e));
+        assert!(!reporter.is_agent_healthy());
+        Ok(())
+    }
+}
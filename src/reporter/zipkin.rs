@@ -0,0 +1,236 @@
+use super::stats::{ReporterCounters, ReporterStats};
+use super::{
+    check_too_large_spans, split_into_packets, Protocol, Reporter, Transport, UdpTransport,
+    DEFAULT_MAX_PACKET_SIZE,
+};
+use crate::span::FinishedSpan;
+use crate::thrift::agent::EmitZipkinBatchNotification;
+use crate::thrift::zipkincore;
+use crate::Result;
+use std::net::SocketAddr;
+use thrift_codec::data::Struct;
+use thrift_codec::message::Message;
+
+/// The port on which the agent accepts zipkincore.thrift over compact thrift protocol.
+const DEFAULT_PORT: u16 = 5775;
+
+/// Reporter for the agent which accepts zipkincore.thrift over compact thrift protocol
+/// (i.e., the `emitZipkinBatch` method).
+///
+/// This is useful for legacy agents which only support the Zipkin thrift format.
+///
+/// # Examples
+///
+/// ```
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::ZipkinCompactReporter;
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// let spans = span_rx.try_iter().collect::<Vec<_>>();
+///
+/// let reporter = ZipkinCompactReporter::new("sample_service").unwrap();
+/// reporter.report(&spans).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ZipkinCompactReporter<T = UdpTransport> {
+    transport: T,
+    endpoint: zipkincore::Endpoint,
+    max_packet_size: usize,
+    counters: ReporterCounters,
+}
+impl ZipkinCompactReporter {
+    /// Makes a new `ZipkinCompactReporter` instance.
+    ///
+    /// # Errors
+    ///
+    /// If the UDP socket used to report spans can not be bound to `127.0.0.1:0`,
+    /// it will return an error which has the kind `ErrorKind::Other`.
+    pub fn new(service_name: &str) -> Result<Self> {
+        let agent = SocketAddr::from(([127, 0, 0, 1], DEFAULT_PORT));
+        let transport = track!(UdpTransport::new(agent))?;
+        Ok(Self::with_transport(service_name, transport))
+    }
+
+    /// Sets the address of the report destination agent to `addr`.
+    ///
+    /// The default address is `127.0.0.1:5775`.
+    ///
+    /// Note that you may also need to call `set_reporter_addr` if the `addr` is IPv6 or non localhost address.
+    pub fn set_agent_addr(&mut self, addr: SocketAddr) {
+        self.transport.set_agent_addr(addr);
+    }
+
+    /// Sets the address of the report destination agent to `host` (e.g., `"jaeger-agent:5775"`).
+    ///
+    /// # Errors
+    ///
+    /// If `host` can not be resolved, this method will return an error.
+    pub fn set_agent_host(&mut self, host: &str) -> Result<()> {
+        track!(self.transport.set_agent_host(host))
+    }
+
+    /// Sets the address to which the reporter bind.
+    ///
+    /// The default address is `127.0.0.1:0`.
+    pub fn set_reporter_addr(&mut self, addr: SocketAddr) -> Result<()> {
+        track!(self.transport.set_reporter_addr(addr))
+    }
+}
+impl<T: Transport> ZipkinCompactReporter<T> {
+    /// Makes a new `ZipkinCompactReporter` instance which sends packets via `transport`.
+    pub fn with_transport(service_name: &str, transport: T) -> Self {
+        #[cfg(not(target_os = "android"))]
+        let ip = local_ip_address::local_ip().ok();
+        #[cfg(target_os = "android")]
+        let ip = None;
+
+        ZipkinCompactReporter {
+            transport,
+            endpoint: zipkincore::Endpoint::new(service_name, ip),
+            max_packet_size: DEFAULT_MAX_PACKET_SIZE,
+            counters: ReporterCounters::default(),
+        }
+    }
+
+    /// Returns a reference to the transport of this reporter.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Sets the endpoint recorded as the host of the annotations.
+    ///
+    /// The default endpoint has the service name and the local IP address of this process.
+    pub fn set_endpoint(&mut self, endpoint: zipkincore::Endpoint) {
+        self.endpoint = endpoint;
+    }
+
+    /// Sets the maximum size of a packet sent to the agent.
+    ///
+    /// The default value is `65000`.
+    pub fn set_max_packet_size(&mut self, size: usize) {
+        self.max_packet_size = size;
+    }
+
+    /// Returns the snapshot of the statistics of this reporter.
+    pub fn stats(&self) -> ReporterStats {
+        self.counters.snapshot()
+    }
+
+    /// Reports `spans`.
+    ///
+    /// The spans are split into multiple packets if they do not fit in a packet.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to encode `spans` to the thrift compact format (i.e., a bug of this crate),
+    /// this method will return an error which has the kind `ErrorKind::InvalidInput`.
+    ///
+    /// If some of `spans` are too large to fit in a packet even alone,
+    /// they are dropped and this method will return an error which has the kind `ErrorKind::InvalidInput`
+    /// after sending the remaining spans.
+    ///
+    /// If it fails to send the encoded binary to the jaeger agent,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        let spans = spans
+            .iter()
+            .map(|span| {
+                let mut span = zipkincore::Span::from(span);
+                span.set_endpoint(&self.endpoint);
+                span
+            })
+            .collect::<Vec<_>>();
+        let (packets, dropped) = match self.split_into_packets(spans) {
+            Ok(x) => x,
+            Err(e) => {
+                self.counters.record_encode_failure();
+                return Err(track!(e));
+            }
+        };
+
+        let mut result = Ok(());
+        for spans in packets {
+            let n = spans.len();
+            let bytes = match self.encode(spans) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    self.counters.record_encode_failure();
+                    return Err(track!(e));
+                }
+            };
+            match self.transport.send(&bytes) {
+                Ok(()) => self.counters.record_sent(bytes.len(), n),
+                Err(e) => {
+                    self.counters.record_send_failure();
+                    result = Err(track!(e));
+                }
+            }
+        }
+        track!(check_too_large_spans(dropped, self.max_packet_size))?;
+        result
+    }
+
+    fn encode(&self, spans: Vec<zipkincore::Span>) -> Result<Vec<u8>> {
+        let message = Message::from(EmitZipkinBatchNotification { spans });
+        track!(Protocol::Compact.encode(&message))
+    }
+
+    fn split_into_packets(
+        &self,
+        spans: Vec<zipkincore::Span>,
+    ) -> Result<(Vec<Vec<zipkincore::Span>>, usize)> {
+        let overhead = track!(self.encode(Vec::new()))?.len();
+        track!(split_into_packets(
+            spans,
+            overhead,
+            self.max_packet_size,
+            |span| Ok(track!(Protocol::Compact.encode(&Struct::from(span.clone())))?.len())
+        ))
+    }
+}
+impl<T: Transport> Reporter for ZipkinCompactReporter<T> {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(ZipkinCompactReporter::report(self, spans))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use std::net::UdpSocket;
+    use std::time::Duration;
+    use thrift_codec::data::Data;
+    use thrift_codec::CompactDecode;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn zipkin_compact_reporter_works() -> TestResult {
+        let agent = track_any_err!(UdpSocket::bind("127.0.0.1:0"))?;
+        track_any_err!(agent.set_read_timeout(Some(Duration::from_secs(5))))?;
+
+        let mut reporter = track!(ZipkinCompactReporter::new("zipkin_test"))?;
+        reporter.set_agent_addr(track_any_err!(agent.local_addr())?);
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer.span("foo").start();
+        tracer.span("bar").start();
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let mut buf = [0; 65536];
+        let size = track_any_err!(agent.recv(&mut buf))?;
+        let message = track_any_err!(Message::compact_decode(&mut &buf[..size]))?;
+        assert_eq!(message.method_name(), "emitZipkinBatch");
+        match message.body().fields()[0].data() {
+            Data::List(spans) => assert_eq!(spans.len(), 2),
+            data => panic!("unexpected data: {:?}", data),
+        }
+        assert_eq!(reporter.stats().spans_sent, 2);
+        Ok(())
+    }
+}
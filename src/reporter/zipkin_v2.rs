@@ -0,0 +1,311 @@
+use super::stats::{ReporterCounters, ReporterStats};
+use super::{HttpTransport, Reporter, Transport};
+use crate::json::{self, Json};
+use crate::span::{FinishedSpan, FLAG_DEBUG};
+use crate::thrift::jaeger;
+use crate::Result;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Reporter which sends spans to Zipkin in the v2 JSON format (i.e., `/api/v2/spans`).
+///
+/// The spans are converted as follows:
+/// - `traceId` consists of 16 hex characters if the high 64 bits of the trace id are zero, 32 otherwise
+/// - The `span.kind` tag becomes `kind` if its value is `client`, `server`, `producer` or `consumer`
+///   (e.g., `"CLIENT"`), otherwise it is kept as a tag
+/// - Logs become annotations, and the other tags become `tags` (the values are stringified)
+/// - `localEndpoint` has the service name and the local IP address of this process
+///
+/// # Examples
+///
+/// ```no_run
+/// use rustracing::sampler::AllSampler;
+/// use rustracing_jaeger::Tracer;
+/// use rustracing_jaeger::reporter::ZipkinV2Reporter;
+///
+/// let (tracer, span_rx) = Tracer::new(AllSampler);
+/// {
+///     let _span = tracer.span("sample_op").start();
+/// }
+/// let spans = span_rx.try_iter().collect::<Vec<_>>();
+///
+/// let reporter =
+///     ZipkinV2Reporter::new("sample_service", "http://zipkin:9411/api/v2/spans").unwrap();
+/// reporter.report(&spans).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ZipkinV2Reporter {
+    transport: HttpTransport,
+    service_name: String,
+    local_ip: Option<IpAddr>,
+    counters: ReporterCounters,
+}
+impl ZipkinV2Reporter {
+    /// Makes a new `ZipkinV2Reporter` instance which sends spans to `url`
+    /// (e.g., `"http://127.0.0.1:9411/api/v2/spans"`).
+    ///
+    /// # Errors
+    ///
+    /// If `url` is not a valid `http://` URL,
+    /// this function will return an error which has the kind `ErrorKind::InvalidInput`.
+    pub fn new(service_name: &str, url: &str) -> Result<Self> {
+        let mut transport = track!(HttpTransport::new(url))?;
+        transport.set_content_type("application/json");
+
+        #[cfg(not(target_os = "android"))]
+        let local_ip = local_ip_address::local_ip().ok();
+        #[cfg(target_os = "android")]
+        let local_ip = None;
+
+        Ok(ZipkinV2Reporter {
+            transport,
+            service_name: service_name.to_owned(),
+            local_ip,
+            counters: ReporterCounters::default(),
+        })
+    }
+
+    /// Returns a reference to the transport of this reporter.
+    pub fn transport(&self) -> &HttpTransport {
+        &self.transport
+    }
+
+    /// Returns the snapshot of the statistics of this reporter.
+    pub fn stats(&self) -> ReporterStats {
+        self.counters.snapshot()
+    }
+
+    /// Sets the IP address recorded in `localEndpoint`.
+    ///
+    /// If `None` is given, the address is omitted.
+    /// The default value is the local IP address of this process.
+    pub fn set_local_ip(&mut self, ip: Option<IpAddr>) {
+        self.local_ip = ip;
+    }
+
+    /// Adds the HTTP header `name: value` to each request (e.g., for authentication).
+    ///
+    /// # Errors
+    ///
+    /// If `name` is not a valid header name, `name` is one of the headers written by the transport itself
+    /// (i.e., `Host`, `Content-Length`, `Connection` and `Transfer-Encoding`) or `value` contains CR, LF or NUL,
+    /// this method will return an error which has the kind `ErrorKind::InvalidInput`.
+    pub fn set_header(&mut self, name: &str, value: &str) -> Result<()> {
+        track!(self.transport.set_header(name, value))
+    }
+
+    /// Sets the timeout of each of connecting, sending a request and receiving the response.
+    ///
+    /// The default value is `Duration::from_secs(5)`.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.transport.set_timeout(timeout);
+    }
+
+    /// Reports `spans`.
+    ///
+    /// All of `spans` are sent by a single request.
+    ///
+    /// # Errors
+    ///
+    /// If it fails to send the request or Zipkin responds with a non-2xx status,
+    /// this method will return an error which has the kind `ErrorKind::Other`.
+    pub fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        let bytes = self.encode(spans).into_bytes();
+        match self.transport.send(&bytes) {
+            Ok(()) => {
+                self.counters.record_sent(bytes.len(), spans.len());
+                Ok(())
+            }
+            Err(e) => {
+                self.counters.record_send_failure();
+                Err(track!(e))
+            }
+        }
+    }
+
+    fn encode(&self, spans: &[FinishedSpan]) -> String {
+        let mut endpoint = Json::object().with("serviceName", self.service_name.as_str());
+        match self.local_ip {
+            Some(IpAddr::V4(ip)) => endpoint.insert("ipv4", ip.to_string()),
+            Some(IpAddr::V6(ip)) => endpoint.insert("ipv6", ip.to_string()),
+            None => {}
+        }
+        let spans = spans
+            .iter()
+            .map(|span| span_to_json(jaeger::Span::from(span), endpoint.clone()))
+            .collect::<Vec<_>>();
+        Json::from(spans).to_string()
+    }
+}
+impl Reporter for ZipkinV2Reporter {
+    fn report(&self, spans: &[FinishedSpan]) -> Result<()> {
+        track!(ZipkinV2Reporter::report(self, spans))
+    }
+}
+
+fn span_to_json(span: jaeger::Span, local_endpoint: Json) -> Json {
+    let mut json = Json::object()
+        .with(
+            "traceId",
+            json::trace_id(span.trace_id_high, span.trace_id_low),
+        )
+        .with("id", json::span_id(span.span_id));
+    if span.parent_span_id != 0 {
+        json.insert("parentId", json::span_id(span.parent_span_id));
+    }
+    json.insert("name", span.operation_name);
+
+    let mut tags = Json::object();
+    for tag in span.tags {
+        let kind = match &tag {
+            jaeger::Tag::String { key, value } if key == "span.kind" => zipkin_kind(value),
+            _ => None,
+        };
+        match kind {
+            Some(kind) => json.insert("kind", kind),
+            None => tags.insert(tag.key(), json::tag_value_string(&tag)),
+        }
+    }
+
+    json = json
+        .with("timestamp", span.start_time)
+        .with("duration", span.duration)
+        .with("localEndpoint", local_endpoint);
+    if span.flags & i32::from(FLAG_DEBUG) != 0 {
+        json.insert("debug", true);
+    }
+    if !span.logs.is_empty() {
+        let annotations = span
+            .logs
+            .into_iter()
+            .map(|log| {
+                Json::object()
+                    .with("timestamp", log.timestamp)
+                    .with("value", annotation_value(log.fields))
+            })
+            .collect::<Vec<_>>();
+        json.insert("annotations", annotations);
+    }
+    if tags != Json::object() {
+        json.insert("tags", tags);
+    }
+    json
+}
+
+/// Returns the Zipkin v2 `kind` corresponding to the value of the `span.kind` tag.
+fn zipkin_kind(span_kind: &str) -> Option<&'static str> {
+    match span_kind {
+        "client" => Some("CLIENT"),
+        "server" => Some("SERVER"),
+        "producer" => Some("PRODUCER"),
+        "consumer" => Some("CONSUMER"),
+        _ => None,
+    }
+}
+
+/// Returns the value of the only field, or `key=value` pairs separated by spaces.
+fn annotation_value(fields: Vec<jaeger::Tag>) -> String {
+    if let [field] = &fields[..] {
+        json::tag_value_string(field)
+    } else {
+        fields
+            .iter()
+            .map(|field| format!("{}={}", field.key(), json::tag_value_string(field)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::test_server;
+    use crate::span::{SpanContextStateBuilder, TraceId};
+    use crate::Tracer;
+    use rustracing::log::LogField;
+    use rustracing::sampler::AllSampler;
+    use rustracing::tag::{StdTag, Tag};
+    use std::net::Ipv4Addr;
+    use trackable::result::TestResult;
+
+    #[test]
+    fn zipkin_v2_reporter_works() -> TestResult {
+        let (addr, rx) = track!(test_server::start(202))?;
+        let url = format!("http://{}/api/v2/spans", addr);
+        let mut reporter = track!(ZipkinV2Reporter::new("zipkin_test", &url))?;
+        reporter.set_local_ip(Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))));
+
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        {
+            let state = SpanContextStateBuilder::new()
+                .trace_id(TraceId {
+                    high: 0,
+                    low: 0xabc,
+                })
+                .span_id(0x10)
+                .finish();
+            let parent = tracer.span("parent").start_with_state(state);
+            let mut child = tracer
+                .span("child")
+                .child_of(&parent)
+                .tag(StdTag::span_kind("client"))
+                .tag(Tag::new("n", 1))
+                .start();
+            child.log(|log| {
+                log.std().event("foo");
+            });
+            child.log(|log| {
+                log.field(LogField::new("a", "b"));
+                log.field(LogField::new("c", "d"));
+            });
+        }
+        track!(reporter.report(&span_rx.try_iter().collect::<Vec<_>>()))?;
+
+        let request = track_any_err!(rx.recv_timeout(Duration::from_secs(5)))?;
+        assert_eq!(request.path, "/api/v2/spans");
+        assert_eq!(request.header("content-type"), Some("application/json"));
+
+        let body = track_any_err!(String::from_utf8(request.body))?;
+        assert!(body.starts_with(r#"[{"traceId":"0000000000000abc","id":""#));
+        assert!(body.contains(r#","parentId":"0000000000000010","name":"child","kind":"CLIENT","#));
+        assert!(body.contains(
+            r#","localEndpoint":{"serviceName":"zipkin_test","ipv4":"10.0.0.1"},"annotations":[{"#
+        ));
+        assert!(body.contains(r#","value":"foo"},{"#));
+        assert!(body.contains(r#","value":"a=b c=d"}],"tags":{"n":"1"}}"#));
+        assert!(body
+            .contains(r#"{"traceId":"0000000000000abc","id":"0000000000000010","name":"parent","#));
+        assert_eq!(reporter.stats().spans_sent, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn long_trace_id_and_debug_flag_work() {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        let state = SpanContextStateBuilder::new()
+            .trace_id(TraceId { high: 1, low: 2 })
+            .debug_id("foo".to_owned())
+            .finish();
+        tracer.span("foo").start_with_state(state);
+        let span = jaeger::Span::from(&span_rx.try_recv().unwrap());
+
+        let json = span_to_json(span, Json::object()).to_string();
+        assert!(json.starts_with(r#"{"traceId":"00000000000000010000000000000002","#));
+        assert!(json.contains(r#""debug":true"#));
+        assert!(json.contains(r#""tags":{"jaeger-debug-id":"foo"}"#));
+    }
+
+    #[test]
+    fn non_standard_span_kind_is_kept_as_tag() {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        tracer
+            .span("foo")
+            .tag(StdTag::span_kind("internal"))
+            .start();
+        let span = jaeger::Span::from(&span_rx.try_recv().unwrap());
+
+        let json = span_to_json(span, Json::object()).to_string();
+        assert!(!json.contains(r#""kind":"#));
+        assert!(json.contains(r#""tags":{"span.kind":"internal"}"#));
+    }
+}
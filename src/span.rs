@@ -77,7 +77,7 @@ pub type SpanContext = rustracing::span::SpanContext<SpanContextState>;
 pub type SpanReference = rustracing::span::SpanReference<SpanContextState>;
 
 const FLAG_SAMPLED: u8 = 0b01;
-const FLAG_DEBUG: u8 = 0b10;
+pub(crate) const FLAG_DEBUG: u8 = 0b10;
 
 /// Unique 128bit identifier of a trace.
 ///
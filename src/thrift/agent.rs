@@ -1,10 +1,11 @@
 //! Thrift components defined in [agent.thrift].
 //!
 //! [agent.thrift]: https://github.com/uber/jaeger-idl/blob/master/thrift/agent.thrift.
-use thrift_codec::data::Struct;
+use thrift_codec::data::{List, Struct};
 use thrift_codec::message::Message;
 
 use crate::thrift::jaeger::Batch;
+use crate::thrift::zipkincore;
 
 /// `emitBatch` message defined in [agent.thrift].
 ///
@@ -19,3 +20,18 @@ impl From<EmitBatchNotification> for Message {
         Message::oneway("emitBatch", 0, Struct::from((Struct::from(f.batch),)))
     }
 }
+
+/// `emitZipkinBatch` message defined in [agent.thrift].
+///
+/// [agent.thrift]: https://github.com/uber/jaeger-idl/blob/master/thrift/agent.thrift
+#[derive(Debug, Clone)]
+pub struct EmitZipkinBatchNotification {
+    /// `spans` argument.
+    pub spans: Vec<zipkincore::Span>,
+}
+impl From<EmitZipkinBatchNotification> for Message {
+    fn from(f: EmitZipkinBatchNotification) -> Self {
+        let spans = List::from(f.spans.into_iter().map(Struct::from).collect::<Vec<_>>());
+        Message::oneway("emitZipkinBatch", 0, Struct::from((spans,)))
+    }
+}
@@ -1,9 +1,10 @@
 //! Thrift components defined in [jaeger.thrift].
 //!
 //! [jaeger.thrift]: https://github.com/uber/jaeger-idl/blob/master/thrift/jaeger.thrift
+use super::elapsed;
 use crate::constants;
 use crate::span::{FinishedSpan, SpanReference};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::UNIX_EPOCH;
 use thrift_codec::data::{Field, List, Struct};
 
 /// `TagKind` denotes the kind of a `Tag`'s value.
@@ -263,15 +264,6 @@ impl<'a> From<&'a FinishedSpan> for Span {
     }
 }
 
-fn elapsed(start: SystemTime, finish: SystemTime) -> i64 {
-    if let Ok(d) = finish.duration_since(start) {
-        (d.as_secs() * 1_000_000 + u64::from(d.subsec_nanos()) / 1000) as i64
-    } else {
-        let d = start.duration_since(finish).expect("Never fails");
-        -((d.as_secs() * 1_000_000 + u64::from(d.subsec_nanos()) / 1000) as i64)
-    }
-}
-
 /// `Process` describes the traced process/service that emits spans.
 #[derive(Debug, Clone)]
 pub struct Process {
@@ -292,18 +284,57 @@ impl From<Process> for Struct {
     }
 }
 
+/// `ClientStats` captures the client-side counters of the dropped spans.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    /// The number of the spans dropped because the queue was full.
+    pub full_queue_dropped_spans: i64,
+
+    /// The number of the spans dropped because they were too large to be sent.
+    pub too_large_dropped_spans: i64,
+
+    /// The number of the spans which failed to be emitted to the agent.
+    pub failed_to_emit_spans: i64,
+}
+impl From<ClientStats> for Struct {
+    fn from(f: ClientStats) -> Self {
+        Struct::from((
+            f.full_queue_dropped_spans,
+            f.too_large_dropped_spans,
+            f.failed_to_emit_spans,
+        ))
+    }
+}
+
 /// `Batch` is a collection of spans reported out of process.
 #[derive(Debug, Clone)]
 #[allow(missing_docs)]
 pub struct Batch {
     pub process: Process,
     pub spans: Vec<Span>,
+
+    /// The sequence number of this batch.
+    ///
+    /// It is incremented for each batch emitted by a client.
+    pub seq_no: Option<i64>,
+
+    /// The client-side statistics at the time this batch is emitted.
+    pub stats: Option<ClientStats>,
 }
 impl From<Batch> for Struct {
     fn from(f: Batch) -> Self {
-        Struct::from((
-            Struct::from(f.process),
+        let mut fields = Vec::with_capacity(4);
+        fields.push(Field::new(1, Struct::from(f.process)));
+        fields.push(Field::new(
+            2,
             List::from(f.spans.into_iter().map(Struct::from).collect::<Vec<_>>()),
-        ))
+        ));
+        if let Some(seq_no) = f.seq_no {
+            fields.push(Field::new(3, seq_no));
+        }
+        if let Some(stats) = f.stats {
+            fields.push(Field::new(4, Struct::from(stats)));
+        }
+        Struct::new(fields)
     }
 }
@@ -1,3 +1,16 @@
 //! Thrift messages for Jaeger.
 pub mod agent;
 pub mod jaeger;
+pub mod zipkincore;
+
+use std::time::SystemTime;
+
+/// Returns the microseconds from `start` to `finish` (negative if `finish` is earlier than `start`).
+fn elapsed(start: SystemTime, finish: SystemTime) -> i64 {
+    if let Ok(d) = finish.duration_since(start) {
+        (d.as_secs() * 1_000_000 + u64::from(d.subsec_nanos()) / 1000) as i64
+    } else {
+        let d = start.duration_since(finish).expect("Never fails");
+        -((d.as_secs() * 1_000_000 + u64::from(d.subsec_nanos()) / 1000) as i64)
+    }
+}
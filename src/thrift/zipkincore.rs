@@ -0,0 +1,399 @@
+//! Thrift components defined in [zipkincore.thrift].
+//!
+//! [zipkincore.thrift]: https://github.com/uber/jaeger-idl/blob/master/thrift/zipkincore.thrift
+use super::elapsed;
+use crate::constants;
+use crate::span::FinishedSpan;
+use rustracing::tag::TagValue;
+use std::net::IpAddr;
+use std::time::UNIX_EPOCH;
+use thrift_codec::data::{Field, List, Struct};
+
+/// The annotation value which indicates that the client has sent the request.
+pub const CLIENT_SEND: &str = "cs";
+
+/// The annotation value which indicates that the client has received the response.
+pub const CLIENT_RECV: &str = "cr";
+
+/// The annotation value which indicates that the server has received the request.
+pub const SERVER_RECV: &str = "sr";
+
+/// The annotation value which indicates that the server has sent the response.
+pub const SERVER_SEND: &str = "ss";
+
+/// The annotation value which indicates that the producer has sent the message.
+pub const MESSAGE_SEND: &str = "ms";
+
+/// The annotation value which indicates that the consumer has received the message.
+pub const MESSAGE_RECV: &str = "mr";
+
+/// The binary annotation key used to identify the local component of a non RPC span.
+pub const LOCAL_COMPONENT: &str = "lc";
+
+/// `Endpoint` indicates the network context of a service recording an annotation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    /// IPv4 host address packed into 4 bytes.
+    pub ipv4: i32,
+
+    /// IPv4 port or `0` if unknown.
+    pub port: i16,
+
+    /// The name of the service.
+    pub service_name: String,
+
+    /// IPv6 host address packed into 16 bytes.
+    pub ipv6: Option<Vec<u8>>,
+}
+impl Endpoint {
+    /// Makes a new `Endpoint` instance of the service `service_name` running on `ip`.
+    pub fn new(service_name: &str, ip: Option<IpAddr>) -> Self {
+        let (ipv4, ipv6) = match ip {
+            Some(IpAddr::V4(ip)) => (u32::from(ip) as i32, None),
+            Some(IpAddr::V6(ip)) => (0, Some(ip.octets().to_vec())),
+            None => (0, None),
+        };
+        Endpoint {
+            ipv4,
+            port: 0,
+            service_name: service_name.to_owned(),
+            ipv6,
+        }
+    }
+}
+impl From<Endpoint> for Struct {
+    fn from(f: Endpoint) -> Self {
+        let mut fields = vec![
+            Field::new(1, f.ipv4),
+            Field::new(2, f.port),
+            Field::new(3, f.service_name),
+        ];
+        if let Some(ipv6) = f.ipv6 {
+            fields.push(Field::new(4, ipv6));
+        }
+        Struct::new(fields)
+    }
+}
+
+/// `Annotation` associates an event that explains latency with a timestamp.
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    /// Microseconds from epoch.
+    pub timestamp: i64,
+
+    /// Usually a short tag indicating an event, like `"sr"` or `"finagle.retry"`.
+    pub value: String,
+
+    /// The host that recorded this annotation.
+    pub host: Option<Endpoint>,
+}
+impl From<Annotation> for Struct {
+    fn from(f: Annotation) -> Self {
+        let mut fields = vec![Field::new(1, f.timestamp), Field::new(2, f.value)];
+        if let Some(host) = f.host {
+            fields.push(Field::new(3, Struct::from(host)));
+        }
+        Struct::new(fields)
+    }
+}
+
+/// The type of the value of a `BinaryAnnotation`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(missing_docs)]
+pub enum AnnotationType {
+    Bool = 0,
+    Bytes = 1,
+    I16 = 2,
+    I32 = 3,
+    I64 = 4,
+    Double = 5,
+    String = 6,
+}
+
+/// `BinaryAnnotation` is a tagged value which adds context to a span.
+#[derive(Debug, Clone)]
+pub struct BinaryAnnotation {
+    /// The name of this annotation.
+    pub key: String,
+
+    /// The value encoded according to `annotation_type` (big-endian for numbers).
+    pub value: Vec<u8>,
+
+    /// The type of `value`.
+    pub annotation_type: AnnotationType,
+
+    /// The host that recorded this annotation.
+    pub host: Option<Endpoint>,
+}
+impl From<BinaryAnnotation> for Struct {
+    fn from(f: BinaryAnnotation) -> Self {
+        let mut fields = vec![
+            Field::new(1, f.key),
+            Field::new(2, f.value),
+            Field::new(3, f.annotation_type as i32),
+        ];
+        if let Some(host) = f.host {
+            fields.push(Field::new(4, Struct::from(host)));
+        }
+        Struct::new(fields)
+    }
+}
+impl<'a> From<&'a rustracing::tag::Tag> for BinaryAnnotation {
+    fn from(f: &'a rustracing::tag::Tag) -> Self {
+        let (value, annotation_type) = match *f.value() {
+            TagValue::Boolean(value) => (vec![value as u8], AnnotationType::Bool),
+            TagValue::Float(value) => (
+                value.to_bits().to_be_bytes().to_vec(),
+                AnnotationType::Double,
+            ),
+            TagValue::Integer(value) => (value.to_be_bytes().to_vec(), AnnotationType::I64),
+            TagValue::String(ref value) => (value.as_bytes().to_vec(), AnnotationType::String),
+        };
+        BinaryAnnotation {
+            key: f.name().to_owned(),
+            value,
+            annotation_type,
+            host: None,
+        }
+    }
+}
+
+/// `Span` is a named unit of work in the Zipkin data model.
+#[derive(Debug, Clone)]
+pub struct Span {
+    /// The least significant 64 bits of a traceID.
+    pub trace_id: i64,
+
+    /// The name of operation.
+    pub name: String,
+
+    /// Unique span id (only unique within a given trace).
+    pub id: i64,
+
+    /// The parent span id, or `None` if this span is a root span.
+    pub parent_id: Option<i64>,
+
+    /// Timestamped events.
+    pub annotations: Vec<Annotation>,
+
+    /// Tags.
+    pub binary_annotations: Vec<BinaryAnnotation>,
+
+    /// `true` if this span is a DEBUG span.
+    pub debug: bool,
+
+    /// Start time of this span in microseconds from epoch.
+    pub timestamp: Option<i64>,
+
+    /// Duration of this span in microseconds.
+    pub duration: Option<i64>,
+
+    /// The most significant 64 bits of a traceID, or `None` when only 64bit IDs are used.
+    pub trace_id_high: Option<i64>,
+}
+impl Span {
+    /// Sets `endpoint` as the host of the annotations of this span.
+    pub fn set_endpoint(&mut self, endpoint: &Endpoint) {
+        for a in &mut self.annotations {
+            a.host = Some(endpoint.clone());
+        }
+        for a in &mut self.binary_annotations {
+            a.host = Some(endpoint.clone());
+        }
+    }
+}
+impl From<Span> for Struct {
+    fn from(f: Span) -> Self {
+        let mut fields = Vec::with_capacity(11);
+        fields.push(Field::new(1, f.trace_id));
+        fields.push(Field::new(3, f.name));
+        fields.push(Field::new(4, f.id));
+        if let Some(parent_id) = f.parent_id {
+            fields.push(Field::new(5, parent_id));
+        }
+        fields.push(Field::new(
+            6,
+            List::from(
+                f.annotations
+                    .into_iter()
+                    .map(Struct::from)
+                    .collect::<Vec<_>>(),
+            ),
+        ));
+        fields.push(Field::new(
+            8,
+            List::from(
+                f.binary_annotations
+                    .into_iter()
+                    .map(Struct::from)
+                    .collect::<Vec<_>>(),
+            ),
+        ));
+        if f.debug {
+            fields.push(Field::new(9, true));
+        }
+        if let Some(timestamp) = f.timestamp {
+            fields.push(Field::new(10, timestamp));
+        }
+        if let Some(duration) = f.duration {
+            fields.push(Field::new(11, duration));
+        }
+        if let Some(trace_id_high) = f.trace_id_high {
+            fields.push(Field::new(12, trace_id_high));
+        }
+        Struct::new(fields)
+    }
+}
+impl<'a> From<&'a FinishedSpan> for Span {
+    fn from(f: &'a FinishedSpan) -> Self {
+        let state = f.context().state();
+        let start_time = elapsed(UNIX_EPOCH, f.start_time());
+        let finish_time = elapsed(UNIX_EPOCH, f.finish_time());
+
+        let kind = f
+            .tags()
+            .iter()
+            .find(|t| t.name() == "span.kind")
+            .and_then(|t| {
+                if let TagValue::String(ref v) = *t.value() {
+                    Some(v.as_ref())
+                } else {
+                    None
+                }
+            });
+        let (start_event, finish_event) = match kind {
+            Some("client") => (Some(CLIENT_SEND), Some(CLIENT_RECV)),
+            Some("server") => (Some(SERVER_RECV), Some(SERVER_SEND)),
+            Some("producer") => (Some(MESSAGE_SEND), None),
+            Some("consumer") => (Some(MESSAGE_RECV), None),
+            _ => (None, None),
+        };
+
+        let mut annotations = Vec::new();
+        if let Some(value) = start_event {
+            annotations.push(annotation(start_time, value.to_owned()));
+        }
+        for log in f.logs() {
+            let value = if let [field] = log.fields() {
+                field.value().to_owned()
+            } else {
+                log.fields()
+                    .iter()
+                    .map(|field| format!("{}={}", field.name(), field.value()))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            annotations.push(annotation(elapsed(UNIX_EPOCH, log.time()), value));
+        }
+        if let Some(value) = finish_event {
+            annotations.push(annotation(finish_time, value.to_owned()));
+        }
+
+        let mut binary_annotations = f
+            .tags()
+            .iter()
+            .filter(|t| t.name() != "span.kind")
+            .map(BinaryAnnotation::from)
+            .collect::<Vec<_>>();
+        if start_event.is_none() {
+            let component = f
+                .tags()
+                .iter()
+                .find(|t| t.name() == "component")
+                .and_then(|t| {
+                    if let TagValue::String(ref v) = *t.value() {
+                        Some(v.as_ref().to_owned())
+                    } else {
+                        None
+                    }
+                });
+            binary_annotations.push(BinaryAnnotation {
+                key: LOCAL_COMPONENT.to_owned(),
+                value: component.unwrap_or_default().into_bytes(),
+                annotation_type: AnnotationType::String,
+                host: None,
+            });
+        }
+        if let Some(id) = state.debug_id() {
+            binary_annotations.push(BinaryAnnotation::from(&rustracing::tag::Tag::new(
+                constants::JAEGER_DEBUG_HEADER,
+                id.to_owned(),
+            )));
+        }
+
+        let trace_id_high = state.trace_id().high as i64;
+        Span {
+            trace_id: state.trace_id().low as i64,
+            name: f.operation_name().to_owned(),
+            id: state.span_id() as i64,
+            parent_id: f
+                .references()
+                .iter()
+                .find(|r| r.span().is_sampled())
+                .map(|r| r.span().span_id() as i64),
+            annotations,
+            binary_annotations,
+            debug: state.flags() & crate::span::FLAG_DEBUG != 0,
+            timestamp: Some(start_time),
+            duration: Some(finish_time - start_time),
+            trace_id_high: if trace_id_high == 0 {
+                None
+            } else {
+                Some(trace_id_high)
+            },
+        }
+    }
+}
+
+fn annotation(timestamp: i64, value: String) -> Annotation {
+    Annotation {
+        timestamp,
+        value,
+        host: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Tracer;
+    use rustracing::sampler::AllSampler;
+    use rustracing::tag::{StdTag, Tag};
+
+    #[test]
+    fn span_conversion_works() {
+        let (tracer, span_rx) = Tracer::new(AllSampler);
+        {
+            let parent = tracer.span("parent").start();
+            let mut span = tracer
+                .span("child")
+                .child_of(&parent)
+                .tag(StdTag::span_kind("client"))
+                .tag(Tag::new("retries", 3))
+                .start();
+            span.log(|log| {
+                log.std().event("retry");
+            });
+        }
+        let spans = span_rx.try_iter().collect::<Vec<_>>();
+        let child = Span::from(&spans[0]);
+        let parent = Span::from(&spans[1]);
+
+        assert_eq!(child.name, "child");
+        assert_eq!(child.parent_id, Some(parent.id));
+        assert_eq!(child.trace_id, parent.trace_id);
+        let values = child
+            .annotations
+            .iter()
+            .map(|a| a.value.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, ["cs", "retry", "cr"]);
+        assert_eq!(child.binary_annotations.len(), 1);
+        assert_eq!(child.binary_annotations[0].key, "retries");
+        assert_eq!(child.binary_annotations[0].value, 3i64.to_be_bytes());
+
+        assert_eq!(parent.parent_id, None);
+        assert!(parent.annotations.is_empty());
+        assert_eq!(parent.binary_annotations[0].key, LOCAL_COMPONENT);
+    }
+}